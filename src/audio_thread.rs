@@ -0,0 +1,134 @@
+use crate::{AllenError, AllenResult, Context};
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+type Command = Box<dyn FnOnce(&Context) + Send>;
+
+/// A dedicated thread owning a [`Context`], for apps which need to drive audio from several threads.
+/// Commands are closures run in submission order on the audio thread, where all AL calls are made.
+/// The thread stops once the `AudioThread` is dropped, after running the commands already submitted.
+pub struct AudioThread {
+    sender: Option<Sender<Command>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioThread {
+    /// Spawns the audio thread, creating its context there with `create_context`,
+    /// e.g. `|| Device::open(None).unwrap().create_context()`.
+    pub fn spawn<F>(create_context: F) -> AllenResult<Self>
+    where
+        F: FnOnce() -> AllenResult<Context> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Command>();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            let context = match create_context() {
+                Ok(context) => {
+                    let _ = ready_sender.send(Ok(()));
+                    context
+                }
+                Err(err) => {
+                    let _ = ready_sender.send(Err(err));
+                    return;
+                }
+            };
+
+            Self::run_commands(&context, receiver);
+        });
+
+        match ready_receiver.recv() {
+            Ok(Ok(())) => Ok(Self {
+                sender: Some(sender),
+                thread: Some(thread),
+            }),
+            Ok(Err(err)) => Err(err),
+            // The thread panicked while creating the context.
+            Err(_) => Err(AllenError::InvalidContext),
+        }
+    }
+
+    fn run_commands(context: &Context, receiver: Receiver<Command>) {
+        for command in receiver {
+            command(context);
+        }
+    }
+
+    /// Queues `command` without waiting for it to run.
+    /// Fails with [`AllenError::InvalidContext`] if the audio thread has stopped, e.g. after a command panicked.
+    pub fn submit<F>(&self, command: F) -> AllenResult<()>
+    where
+        F: FnOnce(&Context) + Send + 'static,
+    {
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(Box::new(command))
+            .map_err(|_| AllenError::InvalidContext)
+    }
+
+    /// Runs `command` on the audio thread and waits for its result.
+    /// Fails with [`AllenError::InvalidContext`] if the audio thread has stopped, e.g. after a command panicked.
+    pub fn call<F, R>(&self, command: F) -> AllenResult<R>
+    where
+        F: FnOnce(&Context) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        self.submit(move |context| {
+            let _ = result_sender.send(command(context));
+        })?;
+
+        result_receiver.recv().map_err(|_| AllenError::InvalidContext)
+    }
+}
+
+impl Drop for AudioThread {
+    fn drop(&mut self) {
+        // Closing the channel ends the command loop.
+        drop(self.sender.take());
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                println!("WARNING: Audio thread panicked!");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Device;
+
+    #[test]
+    fn call_from_another_thread() {
+        let Ok(audio) = AudioThread::spawn(|| {
+            Device::open(None)
+                .ok_or(AllenError::InvalidDevice)?
+                .create_context()
+        }) else {
+            return;
+        };
+
+        let handle = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    audio
+                        .call(|context| context.new_buffer().map(|buffer| buffer.handle()))
+                        .unwrap()
+                })
+                .join()
+                .unwrap()
+        })
+        .unwrap();
+        assert_ne!(handle, 0);
+
+        // Commands run on the thread owning the context.
+        let owner = audio.call(|_| thread::current().id()).unwrap();
+        assert_ne!(owner, thread::current().id());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{AllenResult, Context, Float3, Orientation, Source};
+
+type Update<'a> = Box<dyn FnOnce() -> AllenResult<()> + 'a>;
+
+/// Listener and source property changes collected to be applied together, e.g. once per frame.
+/// With [`Context::set_atomic_updates`], the context is suspended while they're applied, so the mixer never hears
+/// half of a frame's changes. Created with [`Context::batch`].
+pub struct UpdateBatch<'a> {
+    context: Context,
+    updates: Vec<Update<'a>>,
+}
+
+impl<'a> UpdateBatch<'a> {
+    pub(crate) fn new(context: Context) -> Self {
+        Self {
+            context,
+            updates: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, update: impl FnOnce() -> AllenResult<()> + 'a) -> &mut Self {
+        self.updates.push(Box::new(update));
+        self
+    }
+
+    pub fn listener_position(&mut self, value: Float3) -> &mut Self {
+        let listener = self.context.listener();
+        self.push(move || listener.set_position(value))
+    }
+
+    pub fn listener_velocity(&mut self, value: Float3) -> &mut Self {
+        let listener = self.context.listener();
+        self.push(move || listener.set_velocity(value))
+    }
+
+    pub fn listener_orientation(&mut self, value: Orientation) -> &mut Self {
+        let listener = self.context.listener();
+        self.push(move || listener.set_orientation(value))
+    }
+
+    pub fn listener_gain(&mut self, value: f32) -> &mut Self {
+        let listener = self.context.listener();
+        self.push(move || listener.set_gain(value))
+    }
+
+    pub fn source_position(&mut self, source: &'a Source, value: Float3) -> &mut Self {
+        self.push(move || source.set_position(value))
+    }
+
+    pub fn source_velocity(&mut self, source: &'a Source, value: Float3) -> &mut Self {
+        self.push(move || source.set_velocity(value))
+    }
+
+    pub fn source_direction(&mut self, source: &'a Source, value: Float3) -> &mut Self {
+        self.push(move || source.set_direction(value))
+    }
+
+    /// Sets the base gain, see [`Source::set_gain`].
+    pub fn source_gain(&mut self, source: &'a Source, value: f32) -> &mut Self {
+        self.push(move || source.set_gain(value))
+    }
+
+    pub fn source_pitch(&mut self, source: &'a Source, value: f32) -> &mut Self {
+        self.push(move || source.set_pitch(value))
+    }
+
+    /// The number of changes collected.
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    /// Applies the changes in the order they were made, stopping at the first error.
+    pub fn commit(self) -> AllenResult<()> {
+        // A single change is atomic anyway.
+        let _guard = if self.context.atomic_updates() && self.updates.len() > 1 {
+            Some(self.context.suspend_guard()?)
+        } else {
+            None
+        };
+
+        for update in self.updates {
+            update()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_context;
+
+    #[test]
+    fn atomic_commit_processes_once() {
+        let Some(context) = test_context() else { return };
+        context.set_atomic_updates(true);
+        let sources: Vec<_> = (0..16).map(|_| context.new_source().unwrap()).collect();
+
+        let mut batch = context.batch();
+        for (i, source) in sources.iter().enumerate() {
+            batch
+                .source_position(source, [i as f32, 0.0, 0.0])
+                .source_gain(source, 0.5)
+                .source_pitch(source, 1.0);
+        }
+        batch.listener_gain(0.8);
+        assert_eq!(batch.len(), 16 * 3 + 1);
+
+        let before = context.process_calls();
+        batch.commit().unwrap();
+        assert_eq!(context.process_calls() - before, 1);
+    }
+
+    #[test]
+    fn single_update_is_not_suspended() {
+        let Some(context) = test_context() else { return };
+        context.set_atomic_updates(true);
+
+        let mut batch = context.batch();
+        batch.listener_gain(0.8);
+
+        let before = context.process_calls();
+        batch.commit().unwrap();
+        assert_eq!(context.process_calls(), before);
+    }
+}
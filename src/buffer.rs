@@ -1,14 +1,15 @@
 use crate::{
-    check_al_error, check_al_extension, getter, properties::PropertiesContainer,
-    AllenResult, Context,
+    check_al_error, check_al_extension, getter, properties::PropertiesContainer, AllenError,
+    AllenResult, BufferDescriptor, Context, FloatRangeCheck, SampleFormat, Samples,
 };
 use num_derive::{FromPrimitive, ToPrimitive};
-use num_traits::FromPrimitive;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
     ffi::{c_void, CString},
     mem::size_of,
+    ops::Range,
+    time::Duration,
 };
 use oal_sys_windows::*;
 
@@ -21,9 +22,98 @@ pub enum Channels {
     Stereo,
 }
 
-/// Container for OpenAL buffer data to be passed into [`Buffer::data`].
-#[derive(Debug, Clone)]
+impl Channels {
+    /// The number of interleaved samples per frame.
+    pub fn count(&self) -> usize {
+        match self {
+            Channels::Mono => 1,
+            Channels::Stereo => 2,
+        }
+    }
+}
+
+/// Multichannel layouts supported through ``AL_EXT_MCFORMATS``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SurroundLayout {
+    /// Four channels, in OpenAL order FL, FR, BL, BR.
+    Quad,
+    /// 5.1 surround, in OpenAL order FL, FR, FC, LFE, BL, BR.
+    Surround51,
+    /// 6.1 surround, in OpenAL order FL, FR, FC, LFE, BC, SL, SR.
+    Surround61,
+    /// 7.1 surround, in OpenAL order FL, FR, FC, LFE, BL, BR, SL, SR.
+    Surround71,
+}
+
+impl SurroundLayout {
+    /// The speakers in the order OpenAL expects them to be interleaved.
+    pub fn openal_order(&self) -> &'static [Speaker] {
+        use Speaker::*;
+
+        match self {
+            SurroundLayout::Quad => &[FrontLeft, FrontRight, BackLeft, BackRight],
+            SurroundLayout::Surround51 => &[FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight],
+            SurroundLayout::Surround61 => &[FrontLeft, FrontRight, FrontCenter, Lfe, BackCenter, SideLeft, SideRight],
+            SurroundLayout::Surround71 => &[
+                FrontLeft, FrontRight, FrontCenter, Lfe, BackLeft, BackRight, SideLeft, SideRight,
+            ],
+        }
+    }
+
+    /// The number of interleaved samples per frame.
+    pub fn count(&self) -> usize {
+        self.openal_order().len()
+    }
+}
+
+/// A speaker position within a [`SurroundLayout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    BackLeft,
+    BackRight,
+    BackCenter,
+    SideLeft,
+    SideRight,
+}
+
+/// The order in which the speakers of multichannel data are interleaved, if it differs from OpenAL's
+/// (see [`SurroundLayout::openal_order`]), e.g. FL, FC, FR, BL, BR, LFE for 5.1 Vorbis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChannelOrder(pub Vec<Speaker>);
+
+impl ChannelOrder {
+    /// For each channel in OpenAL's order, the index of the same speaker in this order.
+    /// Fails with [`AllenError::InvalidValue`] if this isn't an arrangement of the layout's speakers.
+    fn permutation(&self, layout: SurroundLayout) -> AllenResult<Vec<usize>> {
+        let expected = layout.openal_order();
+        if self.0.len() != expected.len() {
+            return Err(AllenError::InvalidValue);
+        }
+
+        expected
+            .iter()
+            .map(|speaker| {
+                let mut positions = self.0.iter().enumerate().filter(|(_, s)| *s == speaker);
+                match (positions.next(), positions.next()) {
+                    (Some((index, _)), None) => Ok(index),
+                    _ => Err(AllenError::InvalidValue),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Container for OpenAL buffer data to be passed into [`Buffer::data`]. Only serializable, since it borrows its
+/// samples.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum BufferData<'a> {
     /// AL_FORMAT_*8
     I8(&'a [i8]),
@@ -35,7 +125,83 @@ pub enum BufferData<'a> {
     F64(&'a [f64]),
 }
 
-impl BufferData<'_> {
+impl<'a> BufferData<'a> {
+    /// The number of samples (not frames).
+    pub fn len(&self) -> usize {
+        match self {
+            BufferData::I8(data) => data.len(),
+            BufferData::I16(data) => data.len(),
+            BufferData::F32(data) => data.len(),
+            BufferData::F64(data) => data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slice(&self, range: Range<usize>) -> BufferData<'a> {
+        match self {
+            BufferData::I8(data) => BufferData::I8(&data[range]),
+            BufferData::I16(data) => BufferData::I16(&data[range]),
+            BufferData::F32(data) => BufferData::F32(&data[range]),
+            BufferData::F64(data) => BufferData::F64(&data[range]),
+        }
+    }
+
+    /// The first float sample outside of ±1.0, if any. Integer samples are always in range.
+    fn out_of_range_sample(&self) -> Option<f64> {
+        match self {
+            BufferData::I8(_) | BufferData::I16(_) => None,
+            BufferData::F32(data) => data
+                .iter()
+                .find(|sample| sample.abs() > 1.0)
+                .map(|sample| *sample as f64),
+            BufferData::F64(data) => data.iter().find(|sample| sample.abs() > 1.0).copied(),
+        }
+    }
+
+    /// Like [`BufferData::out_of_range_sample`], for native-endian samples of `format` in raw bytes.
+    fn out_of_range_byte_sample(bytes: &[u8], format: SampleFormat) -> Option<f64> {
+        match format {
+            SampleFormat::I8 | SampleFormat::I16 => None,
+            SampleFormat::F32 => bytes
+                .chunks_exact(size_of::<f32>())
+                .map(|sample| f32::from_ne_bytes(sample.try_into().unwrap()) as f64)
+                .find(|sample| sample.abs() > 1.0),
+            SampleFormat::F64 => bytes
+                .chunks_exact(size_of::<f64>())
+                .map(|sample| f64::from_ne_bytes(sample.try_into().unwrap()))
+                .find(|sample| sample.abs() > 1.0),
+        }
+    }
+
+    /// Copies the samples with each frame's channels rearranged; channel `i` of a new frame is channel
+    /// `permutation[i]` of the old one.
+    fn reorder(&self, permutation: &[usize]) -> Samples {
+        fn reorder<T: Copy>(data: &[T], permutation: &[usize]) -> Vec<T> {
+            data.chunks_exact(permutation.len())
+                .flat_map(|frame| permutation.iter().map(move |&channel| frame[channel]))
+                .collect()
+        }
+
+        match self {
+            BufferData::I8(data) => Samples::I8(reorder(data, permutation)),
+            BufferData::I16(data) => Samples::I16(reorder(data, permutation)),
+            BufferData::F32(data) => Samples::F32(reorder(data, permutation)),
+            BufferData::F64(data) => Samples::F64(reorder(data, permutation)),
+        }
+    }
+
+    pub fn format(&self) -> SampleFormat {
+        match self {
+            BufferData::I8(_) => SampleFormat::I8,
+            BufferData::I16(_) => SampleFormat::I16,
+            BufferData::F32(_) => SampleFormat::F32,
+            BufferData::F64(_) => SampleFormat::F64,
+        }
+    }
+
     fn ptr(&self) -> *const c_void {
         match self {
             BufferData::I8(data) => data.as_ptr() as *const c_void,
@@ -55,6 +221,95 @@ impl BufferData<'_> {
     }
 }
 
+/// The AL extension [`Buffer::data`] requires for `format`, if any.
+pub(crate) fn required_extension(format: SampleFormat) -> Option<&'static str> {
+    match format {
+        SampleFormat::I8 | SampleFormat::I16 => None,
+        SampleFormat::F32 => Some("AL_EXT_float32"),
+        SampleFormat::F64 => Some("AL_EXT_double"),
+    }
+}
+
+/// The format to upload `requested` audio as: itself if supported, else the first supported format of `preferences`.
+fn fallback_format(
+    requested: SampleFormat,
+    preferences: &[SampleFormat],
+    mut is_supported: impl FnMut(SampleFormat) -> AllenResult<bool>,
+) -> AllenResult<Option<SampleFormat>> {
+    if is_supported(requested)? {
+        return Ok(Some(requested));
+    }
+
+    for &format in preferences {
+        if is_supported(format)? {
+            return Ok(Some(format));
+        }
+    }
+
+    Ok(None)
+}
+
+/// FNV-1a hash of a buffer's data and layout, identifying it in a [`crate::SceneSnapshot`]. Unlike `std`'s hasher,
+/// this is stable across builds, so snapshots stay valid.
+fn content_hash(bytes: &[u8], format: i32, sample_rate: i32) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let layout = [format.to_le_bytes(), sample_rate.to_le_bytes()];
+    for byte in layout.iter().flatten().chain(bytes) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Catches data declared with the wrong channel count, which would otherwise play at the wrong speed.
+fn check_frames(data: &BufferData, channels: usize) -> AllenResult<()> {
+    if data.len().is_multiple_of(channels) {
+        Ok(())
+    } else {
+        Err(AllenError::ChannelMismatch {
+            samples: data.len(),
+            channels,
+        })
+    }
+}
+
+/// Splits PCM data into chunks of a fixed duration, ready to be uploaded into the buffers of a streaming source.
+/// The last chunk holds whatever remains.
+pub struct StreamChunks<'a> {
+    pcm: BufferData<'a>,
+    chunk_samples: usize,
+    offset: usize,
+}
+
+impl<'a> StreamChunks<'a> {
+    pub fn new(pcm: BufferData<'a>, channels: Channels, sample_rate: i32, chunk: Duration) -> Self {
+        // Always advance by at least one whole frame so iteration terminates.
+        let chunk_frames = ((chunk.as_secs_f64() * sample_rate as f64).round() as usize).max(1);
+
+        Self {
+            pcm,
+            chunk_samples: chunk_frames * channels.count(),
+            offset: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for StreamChunks<'a> {
+    type Item = BufferData<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.pcm.len() {
+            return None;
+        }
+
+        let end = (self.offset + self.chunk_samples).min(self.pcm.len());
+        let chunk = self.pcm.slice(self.offset..end);
+        self.offset = end;
+
+        Some(chunk)
+    }
+}
+
 /// Buffer of audio data.
 /// NOTE: Buffers are bound to a device.
 /// To ensure safety, buffers are not allowed to be cloned. There can only be one instance per-handle.
@@ -146,7 +401,13 @@ impl PropertiesContainer<i32> for Buffer {
 
 impl PropertiesContainer<Channels> for Buffer {
     fn get(&self, param: i32) -> AllenResult<Channels> {
-        Ok(FromPrimitive::from_i32(PropertiesContainer::<i32>::get(self, param)?).unwrap())
+        // AL_CHANNELS is the channel count, not a `Channels` discriminant.
+        match PropertiesContainer::<i32>::get(self, param)? {
+            1 => Ok(Channels::Mono),
+            2 => Ok(Channels::Stereo),
+            // E.g. a surround layout uploaded with `Buffer::data_surround`.
+            _ => Err(AllenError::InvalidValue),
+        }
     }
 
     fn set(&self, _param: i32, _value: Channels) -> AllenResult<()> {
@@ -176,28 +437,140 @@ impl Buffer {
         self.handle
     }
 
-    /// Fills the buffer with data.
+    /// Fills the buffer with data. Fails with [`AllenError::InvalidValue`] if `data` is empty.
     pub fn data(&self, data: BufferData, channels: Channels, sample_rate: i32) -> AllenResult<()> {
+        check_frames(&data, channels.count())?;
+        self.check_range(|| data.out_of_range_sample())?;
+        self.upload_raw(data.format(), channels, data.ptr(), data.size(), sample_rate)
+    }
+
+    /// Fills the buffer with multichannel data. Unless `order` is given, the channels are expected to be
+    /// interleaved in OpenAL's order (see [`SurroundLayout::openal_order`]); otherwise each frame is rearranged
+    /// from `order` to OpenAL's before uploading.
+    /// Requires extension ``AL_EXT_MCFORMATS``; 64-bit samples are not supported.
+    pub fn data_surround(
+        &self,
+        data: BufferData,
+        layout: SurroundLayout,
+        order: Option<&ChannelOrder>,
+        sample_rate: i32,
+    ) -> AllenResult<()> {
+        check_al_extension(&CString::new("AL_EXT_MCFORMATS").unwrap())?;
+
+        if data.is_empty() {
+            return Err(AllenError::InvalidValue);
+        }
+        check_frames(&data, layout.count())?;
+
+        let format = match (data.format(), layout) {
+            (SampleFormat::I8, SurroundLayout::Quad) => AL_FORMAT_QUAD8,
+            (SampleFormat::I8, SurroundLayout::Surround51) => AL_FORMAT_51CHN8,
+            (SampleFormat::I8, SurroundLayout::Surround61) => AL_FORMAT_61CHN8,
+            (SampleFormat::I8, SurroundLayout::Surround71) => AL_FORMAT_71CHN8,
+            (SampleFormat::I16, SurroundLayout::Quad) => AL_FORMAT_QUAD16,
+            (SampleFormat::I16, SurroundLayout::Surround51) => AL_FORMAT_51CHN16,
+            (SampleFormat::I16, SurroundLayout::Surround61) => AL_FORMAT_61CHN16,
+            (SampleFormat::I16, SurroundLayout::Surround71) => AL_FORMAT_71CHN16,
+            (SampleFormat::F32, SurroundLayout::Quad) => AL_FORMAT_QUAD32,
+            (SampleFormat::F32, SurroundLayout::Surround51) => AL_FORMAT_51CHN32,
+            (SampleFormat::F32, SurroundLayout::Surround61) => AL_FORMAT_61CHN32,
+            (SampleFormat::F32, SurroundLayout::Surround71) => AL_FORMAT_71CHN32,
+            (SampleFormat::F64, _) => return Err(AllenError::InvalidValue),
+        };
+
+        self.check_range(|| data.out_of_range_sample())?;
+
+        let reordered = match order {
+            Some(order) => Some(data.reorder(&order.permutation(layout)?)),
+            None => None,
+        };
+        let data = reordered.as_ref().map_or(data, |samples| samples.as_data());
+
+        self.context.check_rate_mismatch(sample_rate);
+
+        let _lock = self.context.make_current();
+
+        self.buffer_data(format, data.ptr(), data.size(), sample_rate)
+    }
+
+    /// Applies [`Context::float_range_check`] to the sample found by `out_of_range_sample`, which is only searched
+    /// for if the check is on.
+    fn check_range(&self, out_of_range_sample: impl FnOnce() -> Option<f64>) -> AllenResult<()> {
+        let range_check = self.context.float_range_check();
+        if range_check != FloatRangeCheck::Off {
+            if let Some(sample) = out_of_range_sample() {
+                if range_check == FloatRangeCheck::Error {
+                    return Err(AllenError::SampleOutOfRange(sample));
+                }
+                println!("WARNING: Uploading float sample {} outside of -1.0..=1.0, which will clip!", sample);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a buffer from raw bytes holding native-endian samples of `format`, e.g. a decoder's output.
+    /// The bytes are handed to OpenAL directly, so no copy is made beyond OpenAL's own.
+    pub fn from_owned_bytes(
+        context: &Context,
+        bytes: Vec<u8>,
+        format: SampleFormat,
+        channels: Channels,
+        sample_rate: i32,
+    ) -> AllenResult<Buffer> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(format.size() * channels.count()) {
+            // Empty, or not a whole number of frames.
+            return Err(AllenError::InvalidValue);
+        }
+
+        let buffer = Buffer::new(context.clone())?;
+        buffer.check_range(|| BufferData::out_of_range_byte_sample(&bytes, format))?;
+        buffer.upload_raw(
+            format,
+            channels,
+            bytes.as_ptr() as *const c_void,
+            bytes.len(),
+            sample_rate,
+        )?;
+
+        Ok(buffer)
+    }
+
+    fn upload_raw(
+        &self,
+        format: SampleFormat,
+        channels: Channels,
+        data: *const c_void,
+        size: usize,
+        sample_rate: i32,
+    ) -> AllenResult<()> {
+        if size == 0 {
+            // Drivers disagree on empty uploads, so refuse them consistently.
+            return Err(AllenError::InvalidValue);
+        }
+
+        self.context.check_rate_mismatch(sample_rate);
+
         let _lock = self.context.make_current();
 
-        let format = match data {
-            BufferData::I8(_) => match channels {
+        let format = match format {
+            SampleFormat::I8 => match channels {
                 Channels::Mono => AL_FORMAT_MONO8,
-                Channels::Stereo => AL_FORMAT_MONO16,
+                Channels::Stereo => AL_FORMAT_STEREO8,
             },
-            BufferData::I16(_) => match channels {
+            SampleFormat::I16 => match channels {
                 Channels::Mono => AL_FORMAT_MONO16,
                 Channels::Stereo => AL_FORMAT_STEREO16,
             },
-            BufferData::F32(_) => {
-                check_al_extension(&CString::new("AL_EXT_float32").unwrap())?;
+            SampleFormat::F32 => {
+                check_al_extension(&CString::new(required_extension(SampleFormat::F32).unwrap()).unwrap())?;
                 match channels {
                     Channels::Mono => AL_FORMAT_MONO_FLOAT32,
                     Channels::Stereo => AL_FORMAT_STEREO_FLOAT32,
                 }
             }
-            BufferData::F64(_) => {
-                check_al_extension(&CString::new("AL_double").unwrap())?;
+            SampleFormat::F64 => {
+                check_al_extension(&CString::new(required_extension(SampleFormat::F64).unwrap()).unwrap())?;
                 match channels {
                     Channels::Mono => AL_FORMAT_MONO_DOUBLE_EXT,
                     Channels::Stereo => AL_FORMAT_STEREO_DOUBLE_EXT,
@@ -205,17 +578,61 @@ impl Buffer {
             }
         };
 
-        unsafe {
-            alBufferData(
-                self.handle,
-                format,
-                data.ptr(),
-                data.size() as i32,
-                sample_rate,
-            )
+        self.buffer_data(format, data, size, sample_rate)
+    }
+
+    /// Uploads with the context locked, recording the content hash used by [`crate::SceneSnapshot`] if
+    /// [`Context::set_track_buffer_content`] is on.
+    fn buffer_data(&self, format: i32, data: *const c_void, size: usize, sample_rate: i32) -> AllenResult<()> {
+        unsafe { alBufferData(self.handle, format, data, size as i32, sample_rate) };
+        check_al_error()?;
+
+        if self.context.track_buffer_content() {
+            let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, size) };
+            self.context
+                .set_buffer_hash(self.handle, content_hash(bytes, format, sample_rate));
+        } else {
+            // The previous content's hash no longer applies.
+            self.context.remove_buffer_hash(self.handle);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Buffer::upload`], but if the descriptor's sample format isn't supported, converts it to the first
+    /// supported format of [`Context::set_format_preferences`] instead. Returns the format uploaded.
+    pub fn upload_with_fallback(&self, descriptor: &BufferDescriptor) -> AllenResult<SampleFormat> {
+        let is_supported = |format: SampleFormat| match required_extension(format) {
+            Some(extension) => {
+                let _lock = self.context.make_current();
+                crate::is_extension_present(&CString::new(extension).unwrap())
+            }
+            None => Ok(true),
         };
 
-        check_al_error()
+        let requested = descriptor.samples.format();
+        let Some(format) = fallback_format(requested, &self.context.format_preferences(), is_supported)? else {
+            return Err(AllenError::MissingExtension(
+                required_extension(requested).unwrap().to_string(),
+            ));
+        };
+
+        if format == requested {
+            self.upload(descriptor)?;
+        } else {
+            self.upload(&descriptor.convert_to(format))?;
+        }
+
+        Ok(format)
+    }
+
+    /// Fills the buffer with the contents of a [`BufferDescriptor`].
+    pub fn upload(&self, descriptor: &BufferDescriptor) -> AllenResult<()> {
+        self.data(
+            descriptor.samples.as_data(),
+            descriptor.channels,
+            descriptor.sample_rate,
+        )
     }
 
     getter!(frequency, i32, AL_FREQUENCY);
@@ -223,6 +640,21 @@ impl Buffer {
     getter!(bits, i32, AL_BITS);
     getter!(channels, Channels, AL_CHANNELS);
 
+    /// The length of the audio in seconds; 0.0 for a buffer without data.
+    pub fn duration(&self) -> AllenResult<f32> {
+        let size = self.size()?;
+        let bits = self.bits()?;
+        let channels = PropertiesContainer::<i32>::get(self, AL_CHANNELS)?;
+        let frequency = self.frequency()?;
+
+        if size <= 0 || bits <= 0 || channels <= 0 || frequency <= 0 {
+            return Ok(0.0);
+        }
+
+        let frames = size / (bits / 8 * channels).max(1);
+        Ok(frames as f32 / frequency as f32)
+    }
+
     // AL_SOFT_loop_points
     pub fn loop_points(&self) -> AllenResult<[i32; 2]> {
         check_al_extension(&CString::new("AL_SOFT_loop_points").unwrap())?;
@@ -254,9 +686,184 @@ impl Buffer {
 
 impl Drop for Buffer {
     fn drop(&mut self) {
+        self.context.remove_buffer_hash(self.handle);
+
+        let _lock = self.context.make_current();
         unsafe { alDeleteBuffers(1, &self.handle) }
         if let Err(err) = check_al_error() {
             println!("WARNING: Buffer drop failed! {}", err);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_context;
+
+    #[test]
+    fn out_of_range_byte_sample() {
+        let bytes: Vec<u8> = [0.5f32, -1.0, 1.5, 2.0].iter().flat_map(|sample| sample.to_ne_bytes()).collect();
+        assert_eq!(BufferData::out_of_range_byte_sample(&bytes, SampleFormat::F32), Some(1.5));
+
+        let bytes: Vec<u8> = [0.25f64, -1.0].iter().flat_map(|sample| sample.to_ne_bytes()).collect();
+        assert_eq!(BufferData::out_of_range_byte_sample(&bytes, SampleFormat::F64), None);
+
+        assert_eq!(BufferData::out_of_range_byte_sample(&[0xff; 4], SampleFormat::I16), None);
+    }
+
+    #[test]
+    fn stream_chunks() {
+        // 2.5 seconds of stereo at 1000 Hz.
+        let pcm: Vec<i16> = (0..5000).map(|sample| sample as i16).collect();
+        let chunks: Vec<_> = StreamChunks::new(
+            BufferData::I16(&pcm),
+            Channels::Stereo,
+            1000,
+            Duration::from_secs(1),
+        )
+        .collect();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].len(), 2000);
+        assert_eq!(chunks[1].len(), 2000);
+        // The half-second remainder.
+        assert_eq!(chunks[2].len(), 1000);
+        match chunks[2] {
+            BufferData::I16(data) => assert_eq!(data[0], 4000),
+            _ => panic!("format changed"),
+        }
+    }
+
+    #[test]
+    fn out_of_range_sample() {
+        assert_eq!(BufferData::F32(&[0.5, -1.0, 2.0]).out_of_range_sample(), Some(2.0));
+        assert_eq!(BufferData::F64(&[0.5, -1.0]).out_of_range_sample(), None);
+        assert_eq!(BufferData::I16(&[i16::MIN]).out_of_range_sample(), None);
+    }
+
+    #[test]
+    fn float_range_check() {
+        let Some(context) = test_context() else { return };
+        let buffer = context.new_buffer().unwrap();
+
+        context.set_float_range_check(FloatRangeCheck::Error);
+        assert!(matches!(
+            buffer.data(BufferData::F32(&[0.5, 2.0]), Channels::Mono, 44100),
+            Err(AllenError::SampleOutOfRange(sample)) if sample == 2.0
+        ));
+        buffer
+            .data(BufferData::F32(&[0.5, -1.0]), Channels::Mono, 44100)
+            .unwrap();
+
+        context.set_float_range_check(FloatRangeCheck::Warn);
+        buffer
+            .data(BufferData::F32(&[0.5, 2.0]), Channels::Mono, 44100)
+            .unwrap();
+    }
+
+    #[test]
+    fn from_owned_bytes() {
+        let Some(context) = test_context() else { return };
+        let bytes: Vec<u8> = (0..200i16).flat_map(|sample| sample.to_ne_bytes()).collect();
+
+        let buffer = Buffer::from_owned_bytes(&context, bytes, SampleFormat::I16, Channels::Stereo, 44100).unwrap();
+        assert_eq!(buffer.size().unwrap(), 400);
+        assert_eq!(buffer.bits().unwrap(), 16);
+        assert_eq!(buffer.channels().unwrap(), Channels::Stereo);
+        assert_eq!(buffer.frequency().unwrap(), 44100);
+
+        let buffer = Buffer::from_owned_bytes(&context, vec![0; 100], SampleFormat::I8, Channels::Stereo, 44100).unwrap();
+        assert_eq!(buffer.bits().unwrap(), 8);
+        assert_eq!(buffer.channels().unwrap(), Channels::Stereo);
+
+        // Not a whole number of stereo 16-bit frames.
+        assert!(Buffer::from_owned_bytes(&context, vec![0; 6], SampleFormat::I16, Channels::Stereo, 44100).is_err());
+    }
+
+    #[test]
+    fn reorder_vorbis_51_to_openal() {
+        use Speaker::*;
+
+        // Vorbis order: FL, FC, FR, BL, BR, LFE.
+        let order = ChannelOrder(vec![FrontLeft, FrontCenter, FrontRight, BackLeft, BackRight, Lfe]);
+        let permutation = order.permutation(SurroundLayout::Surround51).unwrap();
+        assert_eq!(permutation, vec![0, 2, 1, 5, 3, 4]);
+
+        // Two frames, each sample numbered by its speaker in Vorbis order.
+        let frames: [i16; 12] = [10, 11, 12, 13, 14, 15, 20, 21, 22, 23, 24, 25];
+        let reordered = BufferData::I16(&frames).reorder(&permutation);
+        assert_eq!(
+            reordered,
+            Samples::I16(vec![10, 12, 11, 15, 13, 14, 20, 22, 21, 25, 23, 24])
+        );
+    }
+
+    #[test]
+    fn invalid_channel_order() {
+        use Speaker::*;
+
+        let missing = ChannelOrder(vec![FrontLeft, FrontRight, BackLeft]);
+        assert!(missing.permutation(SurroundLayout::Quad).is_err());
+
+        let duplicate = ChannelOrder(vec![FrontLeft, FrontLeft, BackLeft, BackRight]);
+        assert!(duplicate.permutation(SurroundLayout::Quad).is_err());
+    }
+
+    #[test]
+    fn empty_upload_rejected() {
+        let Some(context) = test_context() else { return };
+        let buffer = context.new_buffer().unwrap();
+        assert_eq!(buffer.duration().unwrap(), 0.0);
+
+        assert!(matches!(
+            buffer.data(BufferData::I16(&[]), Channels::Mono, 44100),
+            Err(AllenError::InvalidValue)
+        ));
+        assert!(Buffer::from_owned_bytes(&context, Vec::new(), SampleFormat::I16, Channels::Mono, 44100).is_err());
+
+        buffer.data(BufferData::I16(&[1000]), Channels::Mono, 44100).unwrap();
+        assert!((buffer.duration().unwrap() - 1.0 / 44100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn odd_stereo_slice() {
+        let samples = [0i16; 5];
+        let err = check_frames(&BufferData::I16(&samples), Channels::Stereo.count()).unwrap_err();
+        assert!(matches!(err, AllenError::ChannelMismatch { samples: 5, channels: 2 }));
+        assert_eq!(err.to_string(), "5 samples don't make whole frames of 2 channels");
+
+        assert!(check_frames(&BufferData::I16(&samples[..4]), Channels::Stereo.count()).is_ok());
+        assert!(check_frames(&BufferData::I16(&samples), Channels::Mono.count()).is_ok());
+    }
+
+    #[test]
+    fn fallback_format_follows_preferences() {
+        let without_double = |format| Ok(format != SampleFormat::F64);
+        let preferences = [SampleFormat::I16, SampleFormat::F32];
+
+        assert_eq!(fallback_format(SampleFormat::F64, &preferences, without_double).unwrap(), Some(SampleFormat::I16));
+        assert_eq!(fallback_format(SampleFormat::F32, &preferences, without_double).unwrap(), Some(SampleFormat::F32));
+        assert_eq!(fallback_format(SampleFormat::F64, &[SampleFormat::F64], without_double).unwrap(), None);
+    }
+
+    #[test]
+    fn upload_with_fallback() {
+        let Some(context) = test_context() else { return };
+        context.set_format_preferences(&[SampleFormat::I16, SampleFormat::F32]);
+        let has_double = {
+            let _lock = context.make_current();
+            crate::is_extension_present(&CString::new("AL_EXT_double").unwrap()).unwrap()
+        };
+
+        let buffer = context.new_buffer().unwrap();
+        let descriptor = BufferDescriptor::new(Samples::F64(vec![0.5; 100]), Channels::Mono, 44100);
+        let format = buffer.upload_with_fallback(&descriptor).unwrap();
+        if has_double {
+            assert_eq!(format, SampleFormat::F64);
+        } else {
+            assert_eq!(format, SampleFormat::I16);
+            assert_eq!(buffer.bits().unwrap(), 16);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{AllenResult, Buffer, Context};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+/// Shares buffers between sources by asset id, e.g. a file path, so each sound is only uploaded once.
+/// The manager doesn't keep buffers alive: a buffer is deleted once the last [`Arc`] to it is dropped, including
+/// those held by sources through [`crate::Source::set_shared_buffer`].
+pub struct BufferManager {
+    context: Context,
+    buffers: HashMap<String, Weak<Buffer>>,
+}
+
+impl BufferManager {
+    pub fn new(context: &Context) -> Self {
+        Self {
+            context: context.clone(),
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// The buffer loaded for `id`, if it's still alive.
+    pub fn get(&self, id: &str) -> Option<Arc<Buffer>> {
+        self.buffers.get(id).and_then(Weak::upgrade)
+    }
+
+    /// The buffer loaded for `id`, or a new one filled by `load` (e.g. with [`Buffer::upload`]) if there's none.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn get_or_load(
+        &mut self,
+        id: &str,
+        load: impl FnOnce(&Buffer) -> AllenResult<()>,
+    ) -> AllenResult<Arc<Buffer>> {
+        if let Some(buffer) = self.get(id) {
+            return Ok(buffer);
+        }
+
+        let buffer = self.context.new_buffer()?;
+        load(&buffer)?;
+
+        let buffer = Arc::new(buffer);
+        self.buffers.insert(id.to_string(), Arc::downgrade(&buffer));
+
+        Ok(buffer)
+    }
+
+    /// Whether the buffer for `id` is alive.
+    pub fn is_loaded(&self, id: &str) -> bool {
+        self.buffers
+            .get(id)
+            .is_some_and(|buffer| buffer.strong_count() > 0)
+    }
+
+    /// Forgets the ids whose buffers have been deleted.
+    pub fn prune(&mut self) {
+        self.buffers.retain(|_, buffer| buffer.strong_count() > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_context, BufferData, Channels};
+    use oal_sys_windows::*;
+
+    #[test]
+    fn deleted_after_last_source() {
+        let Some(context) = test_context() else { return };
+        let mut manager = BufferManager::new(&context);
+        let buffer = manager
+            .get_or_load("click.wav", |buffer| buffer.data(BufferData::I16(&[0; 64]), Channels::Mono, 44100))
+            .unwrap();
+        let handle = buffer.handle();
+        let is_buffer = || {
+            let _lock = context.make_current();
+            unsafe { alIsBuffer(handle) != 0 }
+        };
+
+        let first = context.new_source().unwrap();
+        let second = context.new_source().unwrap();
+        first.set_shared_buffer(Some(buffer.clone())).unwrap();
+        second.set_shared_buffer(Some(buffer)).unwrap();
+
+        drop(first);
+        assert!(manager.is_loaded("click.wav"));
+        assert!(is_buffer());
+
+        drop(second);
+        assert!(!manager.is_loaded("click.wav"));
+        assert!(!is_buffer());
+
+        manager.prune();
+        assert!(manager.get("click.wav").is_none());
+    }
+}
@@ -1,28 +1,164 @@
-use crate::{get_string, AllenResult, Buffer, Device, Listener, Source};
+use crate::{
+    buffer::required_extension, check_al_error, get_string, scene, AllenError, AllenResult, Buffer,
+    BufferDescriptor, Device, Effect, EffectSlot, Efx, Filter, GainFactors, LinearResampler, Listener,
+    Orientation, Resampler, SampleFormat, SceneSnapshot, Source, UpdateBatch,
+};
 use lazy_static::lazy_static;
 use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
     ffi::CString,
     ptr,
-    sync::{Arc, Mutex, MutexGuard},
+    rc,
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
 };
 use oal_sys_windows::*;
 
+/// What [`crate::Buffer::data`] does with float samples outside of ±1.0, which clip harshly on playback.
+/// See [`Context::set_float_range_check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatRangeCheck {
+    /// Upload the data as-is.
+    #[default]
+    Off,
+    /// Print a warning, then upload the data.
+    Warn,
+    /// Refuse the upload with [`AllenError::SampleOutOfRange`].
+    Error,
+}
+
+/// A change in the connection of a context's device, returned by [`Context::poll_device_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// The device was lost, e.g. unplugged. The device and its contexts must be reopened to continue playback.
+    Disconnected,
+    /// The device became usable again.
+    Reconnected,
+}
+
+/// The attributes a context was actually created with, see [`Context::actual_attributes`].
+/// Attributes the driver doesn't report are left at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ContextAttributes {
+    /// Output sample rate, in Hz.
+    pub frequency: i32,
+    /// Mixing updates per second.
+    pub refresh: i32,
+    /// Whether the context mixes synchronously, without its own mixing thread.
+    pub sync: bool,
+    pub mono_sources: i32,
+    pub stereo_sources: i32,
+    /// Auxiliary sends per source; zero without ``ALC_EXT_EFX``.
+    pub max_auxiliary_sends: i32,
+}
+
+// ALC_SOFT_output_mode, missing from the bindings.
+const ALC_OUTPUT_MODE_SOFT: i32 = 0x19AC;
+const ALC_STEREO_BASIC_SOFT: i32 = 0x19AE;
+const ALC_STEREO_UHJ_SOFT: i32 = 0x19AF;
+const ALC_STEREO_HRTF_SOFT: i32 = 0x19B2;
+const ALC_MONO_SOFT: i32 = 0x1500;
+const ALC_STEREO_SOFT: i32 = 0x1501;
+const ALC_QUAD_SOFT: i32 = 0x1503;
+const ALC_5POINT1_SOFT: i32 = 0x1504;
+const ALC_6POINT1_SOFT: i32 = 0x1505;
+const ALC_7POINT1_SOFT: i32 = 0x1506;
+
+/// Upper bound for [`Context::probe_max_sources`], for drivers without a practical limit.
+const MAX_PROBED_SOURCES: usize = 4096;
+
 lazy_static! {
     static ref SINGLE_CONTEXT_LOCK: Mutex<()> = Mutex::new(());
 }
 
+/// A mixer category, see [`Context::set_category_gain`].
+struct Category {
+    gain: f32,
+    /// Handle and gain factors of each source registered to the category.
+    sources: Vec<(u32, rc::Weak<Cell<GainFactors>>)>,
+}
+
 pub(crate) struct ContextInner {
     handle: *mut ALCcontext,
     device: Device,
+    efx: Option<Efx>,
+    /// Named effect slots shared through [`Context::effect_slot`].
+    /// Held weakly, since every slot holds onto its context.
+    effect_slots: RefCell<HashMap<String, Weak<EffectSlot>>>,
+    float_range_check: Cell<FloatRangeCheck>,
+    categories: RefCell<HashMap<String, Category>>,
+    /// Ratio set with [`Context::warn_on_rate_mismatch`].
+    rate_mismatch_ratio: Cell<Option<f32>>,
+    /// See [`Context::rate_mismatch_warnings`].
+    rate_mismatch_warnings: Cell<usize>,
+    /// Connection state as of the last [`Context::poll_device_events`].
+    connected: Cell<bool>,
+    max_sources: Cell<Option<usize>>,
+    /// Handles of the context's live sources, in creation order, with their gain factors for scene snapshots.
+    sources: RefCell<Vec<(u32, rc::Weak<Cell<GainFactors>>)>>,
+    atomic_updates: Cell<bool>,
+    /// See [`Context::set_track_buffer_content`].
+    track_buffer_content: Cell<bool>,
+    /// Content hashes of the buffers uploaded while tracking, by handle.
+    buffer_hashes: RefCell<HashMap<u32, u64>>,
+    format_preferences: RefCell<Vec<SampleFormat>>,
+    /// Calls to [`Context::process`], for tests of batching.
+    #[cfg(test)]
+    process_calls: Cell<usize>,
+}
+
+impl ContextInner {
+    fn destroy(&mut self) -> AllenResult<()> {
+        if self.handle.is_null() {
+            return Ok(());
+        }
+
+        // A context can't be destroyed while current. Other contexts current on this thread are left alone.
+        unsafe {
+            let name = CString::new("alcGetThreadContext").unwrap();
+            let get_thread_context: PFNALCGETTHREADCONTEXTPROC =
+                std::mem::transmute(alcGetProcAddress(ptr::null_mut(), name.as_ptr() as *const ALCchar));
+            let name = CString::new("alcSetThreadContext").unwrap();
+            let set_thread_context: PFNALCSETTHREADCONTEXTPROC =
+                std::mem::transmute(alcGetProcAddress(ptr::null_mut(), name.as_ptr() as *const ALCchar));
+            if let (Some(get_thread_context), Some(set_thread_context)) = (get_thread_context, set_thread_context) {
+                if get_thread_context() == self.handle {
+                    set_thread_context(ptr::null_mut());
+                }
+            }
+
+            if alcGetCurrentContext() == self.handle {
+                alcMakeContextCurrent(ptr::null_mut());
+            }
+
+            alcDestroyContext(self.handle);
+        }
+        self.handle = ptr::null_mut();
+
+        // The device is a field, so it's only closed after this.
+        self.device.check_alc_error()
+    }
 }
 
 impl Drop for ContextInner {
     fn drop(&mut self) {
-        unsafe { alcDestroyContext(self.handle) };
-        // TODO: Warn on drop fail.
+        if let Err(err) = self.destroy() {
+            println!("WARNING: Context drop failed! {}", err);
+        }
     }
 }
 
+/// Whether two sample rates are further apart than `ratio`, in either direction.
+fn rates_mismatch(sample_rate: i32, device_rate: i32, ratio: f32) -> bool {
+    let (low, high) = if sample_rate < device_rate {
+        (sample_rate, device_rate)
+    } else {
+        (device_rate, sample_rate)
+    };
+
+    low > 0 && high as f32 / low as f32 > ratio
+}
+
 /// An OpenAL context.
 #[derive(Clone)]
 pub struct Context {
@@ -30,22 +166,83 @@ pub struct Context {
 }
 
 impl Context {
-    pub(crate) fn new(device: Device) -> AllenResult<Context> {
-        let handle = unsafe { alcCreateContext(device.inner.handle, ptr::null()) }; // TODO: support the attrlist parameter.
+    pub(crate) fn new(device: Device, attributes: &[(i32, i32)]) -> AllenResult<Context> {
+        let attrlist = if attributes.is_empty() {
+            None
+        } else {
+            // Zero-terminated list of key/value pairs.
+            let mut attrlist: Vec<i32> = attributes.iter().flat_map(|&(key, value)| [key, value]).collect();
+            attrlist.push(0);
+            Some(attrlist)
+        };
+
+        let handle = unsafe {
+            alcCreateContext(
+                device.inner.handle,
+                attrlist.as_ref().map_or(ptr::null(), |attrlist| attrlist.as_ptr()),
+            )
+        };
 
         if handle == ptr::null_mut() {
-            Err(device.check_alc_error().expect_err("handle is null"))
+            let reason = match device.check_alc_error() {
+                Err(err) => err.to_string(),
+                Ok(()) => "no ALC error was reported".to_string(),
+            };
+
+            Err(AllenError::DeviceError(format!(
+                "failed to create context on device `{}` with attributes {:?}: {}",
+                device.try_device_name().unwrap_or_default(),
+                attributes,
+                reason
+            )))
         } else {
             unsafe {
                 alDistanceModel(AL_LINEAR_DISTANCE_CLAMPED);
             }
 
+            let efx = if device
+                .is_extension_present(&CString::new("ALC_EXT_EFX").unwrap())
+                .unwrap_or(false)
+            {
+                Some(Efx::load())
+            } else {
+                None
+            };
+
             Ok(Self {
-                inner: Arc::new(ContextInner { handle, device }),
+                inner: Arc::new(ContextInner {
+                    handle,
+                    device,
+                    efx,
+                    effect_slots: RefCell::new(HashMap::new()),
+                    float_range_check: Cell::new(FloatRangeCheck::Off),
+                    categories: RefCell::new(HashMap::new()),
+                    rate_mismatch_ratio: Cell::new(None),
+                    rate_mismatch_warnings: Cell::new(0),
+                    connected: Cell::new(true),
+                    max_sources: Cell::new(None),
+                    sources: RefCell::new(Vec::new()),
+                    atomic_updates: Cell::new(false),
+                    track_buffer_content: Cell::new(false),
+                    buffer_hashes: RefCell::new(HashMap::new()),
+                    format_preferences: RefCell::new(vec![SampleFormat::F32, SampleFormat::I16]),
+                    #[cfg(test)]
+                    process_calls: Cell::new(0),
+                }),
             })
         }
     }
 
+    /// Destroys the context now instead of on drop, so failures can be observed. Sources, buffers and other objects
+    /// hold on to their context, so this fails with [`AllenError::InvalidOperation`] while other handles to it
+    /// exist; the context is then destroyed once the last of them is dropped.
+    pub fn destroy(self) -> AllenResult<()> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(mut inner) => inner.destroy(),
+            Err(_) => Err(AllenError::InvalidOperation),
+        }
+    }
+
     /// Locks the current context into self for the entire thread (if not possible, entire process).
     pub fn make_current(&self) -> Option<MutexGuard<()>> {
         // Try for thread first.
@@ -58,7 +255,7 @@ impl Context {
             ))
         };
 
-        if let Some(function) = function {
+        let lock = if let Some(function) = function {
             unsafe {
                 function(self.inner.handle);
             }
@@ -69,8 +266,15 @@ impl Context {
             assert_eq!(true as i8, unsafe {
                 alcMakeContextCurrent(self.inner.handle)
             });
-            Some(SINGLE_CONTEXT_LOCK.lock().unwrap())
-        }
+            // A panic while locked leaves nothing to clean up but the AL error, which is discarded below.
+            Some(SINGLE_CONTEXT_LOCK.lock().unwrap_or_else(PoisonError::into_inner))
+        };
+
+        // alGetError is global state. Discard any error left behind by a call that never checked it (e.g. because
+        // it panicked), so it isn't misattributed to whatever the caller does next.
+        unsafe { alGetError() };
+
+        lock
     }
 
     pub fn is_current(&self) -> bool {
@@ -130,6 +334,424 @@ impl Context {
         Source::new(self.clone())
     }
 
+    /// Creates a buffer from `descriptor`, resampled to the device's frequency with a [`LinearResampler`] if needed,
+    /// so OpenAL doesn't have to resample it on every playback.
+    pub fn upload_matched(&self, descriptor: &BufferDescriptor) -> AllenResult<Buffer> {
+        self.upload_matched_with(descriptor, &LinearResampler)
+    }
+
+    /// Like [`Context::upload_matched`], with a custom resampler.
+    pub fn upload_matched_with(
+        &self,
+        descriptor: &BufferDescriptor,
+        resampler: &dyn Resampler,
+    ) -> AllenResult<Buffer> {
+        let frequency = self.inner.device.frequency()?;
+
+        let buffer = self.new_buffer()?;
+        if descriptor.sample_rate == frequency {
+            buffer.upload(descriptor)?;
+        } else {
+            buffer.upload(&descriptor.resample(frequency, resampler))?;
+        }
+
+        Ok(buffer)
+    }
+
+    /// Creates a new effect. Requires extension ``ALC_EXT_EFX``.
+    pub fn new_effect(&self) -> AllenResult<Effect> {
+        Effect::new(self.clone())
+    }
+
+    /// Creates a new filter. Requires extension ``ALC_EXT_EFX``.
+    pub fn new_filter(&self) -> AllenResult<Filter> {
+        Filter::new(self.clone())
+    }
+
+    /// Creates a new effect slot. Requires extension ``ALC_EXT_EFX``.
+    pub fn new_effect_slot(&self) -> AllenResult<EffectSlot> {
+        EffectSlot::new(self.clone())
+    }
+
+    /// Returns the effect slot registered under `name`, creating it if needed, so that zones sharing a name
+    /// share a slot instead of exhausting the limited slot count.
+    /// A slot stays registered for as long as any returned handle to it is alive.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn effect_slot(&self, name: &str) -> AllenResult<Arc<EffectSlot>> {
+        let mut effect_slots = self.inner.effect_slots.borrow_mut();
+
+        if let Some(slot) = effect_slots.get(name).and_then(Weak::upgrade) {
+            return Ok(slot);
+        }
+
+        let slot = Arc::new(self.new_effect_slot()?);
+        effect_slots.insert(name.to_string(), Arc::downgrade(&slot));
+
+        Ok(slot)
+    }
+
+    /// Sets how buffer uploads of float data treat samples outside of ±1.0. Off by default.
+    pub fn set_float_range_check(&self, check: FloatRangeCheck) {
+        self.inner.float_range_check.set(check);
+    }
+
+    pub fn format_preferences(&self) -> Vec<SampleFormat> {
+        self.inner.format_preferences.borrow().clone()
+    }
+
+    /// The formats [`crate::Buffer::upload_with_fallback`] converts to, in order of preference, when the requested
+    /// one isn't supported. Defaults to `[F32, I16]`; e.g. `[I16, F32]` saves memory instead.
+    pub fn set_format_preferences(&self, order: &[SampleFormat]) {
+        *self.inner.format_preferences.borrow_mut() = order.to_vec();
+    }
+
+    pub fn float_range_check(&self) -> FloatRangeCheck {
+        self.inner.float_range_check.get()
+    }
+
+    /// Sets whether buffer uploads record a hash of their content, which [`SceneSnapshot`]s use to reference
+    /// buffers. Off by default, since it costs a pass over every upload, including streaming chunks. Buffers
+    /// uploaded while off are snapshotted without a buffer.
+    pub fn set_track_buffer_content(&self, track: bool) {
+        self.inner.track_buffer_content.set(track);
+    }
+
+    pub fn track_buffer_content(&self) -> bool {
+        self.inner.track_buffer_content.get()
+    }
+
+    /// The gain of a mixer category; 1.0 unless set.
+    pub fn category_gain(&self, category: &str) -> f32 {
+        self.inner
+            .categories
+            .borrow()
+            .get(category)
+            .map(|category| category.gain)
+            .unwrap_or(1.0)
+    }
+
+    /// Sets the gain of a mixer category, applying it to every source in it (see [`Source::set_category`]).
+    /// The effective gain of a source is the listener gain (the master) × its category gain × its own gain.
+    pub fn set_category_gain(&self, category: &str, gain: f32) -> AllenResult<()> {
+        let mut categories = self.inner.categories.borrow_mut();
+        let category = categories
+            .entry(category.to_string())
+            .or_insert_with(|| Category {
+                gain,
+                sources: Vec::new(),
+            });
+        category.gain = gain;
+        category
+            .sources
+            .retain(|(_, factors)| factors.strong_count() > 0);
+
+        let _lock = self.make_current();
+
+        for (handle, factors) in &category.sources {
+            if let Some(factors) = factors.upgrade() {
+                let mut value = factors.get();
+                value.category = gain;
+                factors.set(value);
+
+                unsafe { alSourcef(*handle, AL_GAIN, value.product()) };
+            }
+        }
+
+        check_al_error()
+    }
+
+    /// Tracks a live source so context-wide operations such as [`Context::reset_audio_state`] can reach it.
+    pub(crate) fn register_source(&self, handle: u32, factors: rc::Weak<Cell<GainFactors>>) {
+        self.inner.sources.borrow_mut().push((handle, factors));
+    }
+
+    pub(crate) fn unregister_source(&self, handle: u32) {
+        self.inner.sources.borrow_mut().retain(|(source, _)| *source != handle);
+    }
+
+    /// Handles of the live sources, in creation order.
+    pub(crate) fn source_handles(&self) -> Vec<u32> {
+        self.inner.sources.borrow().iter().map(|(handle, _)| *handle).collect()
+    }
+
+    pub(crate) fn set_buffer_hash(&self, handle: u32, hash: u64) {
+        self.inner.buffer_hashes.borrow_mut().insert(handle, hash);
+    }
+
+    pub(crate) fn remove_buffer_hash(&self, handle: u32) {
+        self.inner.buffer_hashes.borrow_mut().remove(&handle);
+    }
+
+    pub(crate) fn buffer_hash(&self, handle: u32) -> Option<u64> {
+        self.inner.buffer_hashes.borrow().get(&handle).copied()
+    }
+
+    /// A live buffer whose content has `hash`.
+    pub(crate) fn buffer_by_hash(&self, hash: u64) -> Option<u32> {
+        self.inner
+            .buffer_hashes
+            .borrow()
+            .iter()
+            .find(|(_, buffer_hash)| **buffer_hash == hash)
+            .map(|(handle, _)| *handle)
+    }
+
+    /// Captures the listener and every source, see [`SceneSnapshot`]. Only buffers uploaded with
+    /// [`Context::set_track_buffer_content`] on are referenced, and the queues of streaming sources are left out.
+    pub fn scene_snapshot(&self) -> AllenResult<SceneSnapshot> {
+        let sources = self.inner.sources.borrow().clone();
+        let _lock = self.make_current();
+
+        let listener = scene::capture_listener()?;
+        let sources = sources
+            .into_iter()
+            .map(|(handle, factors)| {
+                let gain = factors.upgrade().map_or(1.0, |factors| factors.get().base);
+                scene::capture_source(handle, gain, |buffer| self.buffer_hash(buffer))
+            })
+            .collect::<AllenResult<_>>()?;
+
+        Ok(SceneSnapshot { listener, sources })
+    }
+
+    /// Reapplies a [`SceneSnapshot`], source by source in creation order, so the context must have as many sources
+    /// as the snapshot ([`AllenError::InvalidValue`] otherwise). Fails with [`AllenError::InvalidName`] if a
+    /// snapshotted buffer no longer exists. Nothing is changed when either check fails. Gains are restored as base
+    /// gains, like [`Source::set_gain`]. Streaming sources, in the snapshot or now, keep their queue and playback
+    /// state; only their other properties are restored.
+    pub fn restore_scene(&self, snapshot: &SceneSnapshot) -> AllenResult<()> {
+        let sources = self.inner.sources.borrow().clone();
+        if sources.len() != snapshot.sources.len() {
+            return Err(AllenError::InvalidValue);
+        }
+
+        let buffers = snapshot
+            .sources
+            .iter()
+            .map(|source| match source.buffer {
+                Some(hash) => self.buffer_by_hash(hash).ok_or(AllenError::InvalidName),
+                None => Ok(0),
+            })
+            .collect::<AllenResult<Vec<_>>>()?;
+
+        let _lock = self.make_current();
+
+        scene::apply_listener(&snapshot.listener)?;
+        for (((handle, factors), source), buffer) in sources.into_iter().zip(&snapshot.sources).zip(buffers) {
+            // As `Source::set_gain` would, so the other gain factors stay applied.
+            let output_gain = match factors.upgrade() {
+                Some(factors) => {
+                    let mut gain = factors.get();
+                    gain.base = source.gain;
+                    factors.set(gain);
+                    gain.product()
+                }
+                None => source.gain,
+            };
+            scene::apply_source(handle, source, buffer, output_gain)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gives a clean slate, e.g. between scenes: stops every source, moves the listener back to the origin with the
+    /// default orientation, velocity and gain, and unloads the effects of the slots from [`Context::effect_slot`].
+    pub fn reset_audio_state(&self) -> AllenResult<()> {
+        {
+            let sources = self.source_handles();
+            let _lock = self.make_current();
+            unsafe { alSourceStopv(sources.len() as i32, sources.as_ptr()) };
+            check_al_error()?;
+        }
+
+        let listener = self.listener();
+        listener.set_position([0.0, 0.0, 0.0])?;
+        listener.set_velocity([0.0, 0.0, 0.0])?;
+        // Facing -Z with +Y up.
+        listener.set_orientation(Orientation {
+            at: [0.0, 0.0, -1.0],
+            up: [0.0, 1.0, 0.0],
+        })?;
+        listener.set_gain(1.0)?;
+
+        let slots: Vec<_> = self
+            .inner
+            .effect_slots
+            .borrow()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for slot in slots {
+            slot.set_effect(None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Registers a source to a category, returning the category's gain.
+    pub(crate) fn register_category_source(
+        &self,
+        category: &str,
+        handle: u32,
+        factors: rc::Weak<Cell<GainFactors>>,
+    ) -> f32 {
+        let mut categories = self.inner.categories.borrow_mut();
+        let category = categories
+            .entry(category.to_string())
+            .or_insert_with(|| Category {
+                gain: 1.0,
+                sources: Vec::new(),
+            });
+        category.sources.push((handle, factors));
+
+        category.gain
+    }
+
+    pub(crate) fn unregister_category_source(&self, category: &str, handle: u32) {
+        if let Some(category) = self.inner.categories.borrow_mut().get_mut(category) {
+            category.sources.retain(|(source, _)| *source != handle);
+        }
+    }
+
+    /// Warns on buffer uploads whose sample rate differs from the device's by more than `ratio` (e.g. `2.0` for
+    /// 22050 Hz on a 48000 Hz device), since real-time resampling that far degrades quality. Off by default.
+    pub fn warn_on_rate_mismatch(&self, ratio: f32) {
+        self.inner.rate_mismatch_ratio.set(Some(ratio));
+    }
+
+    /// Stops the warnings enabled by [`Context::warn_on_rate_mismatch`].
+    pub fn clear_rate_mismatch_warning(&self) {
+        self.inner.rate_mismatch_ratio.set(None);
+    }
+
+    /// How many uploads [`Context::warn_on_rate_mismatch`] has warned about, e.g. to report in telemetry.
+    pub fn rate_mismatch_warnings(&self) -> usize {
+        self.inner.rate_mismatch_warnings.get()
+    }
+
+    pub(crate) fn check_rate_mismatch(&self, sample_rate: i32) {
+        let ratio = match self.inner.rate_mismatch_ratio.get() {
+            Some(ratio) => ratio,
+            None => return,
+        };
+
+        if let Ok(device_rate) = self.inner.device.frequency() {
+            if rates_mismatch(sample_rate, device_rate, ratio) {
+                self.inner
+                    .rate_mismatch_warnings
+                    .set(self.inner.rate_mismatch_warnings.get() + 1);
+                println!(
+                    "WARNING: Uploading a {} Hz buffer to a {} Hz device; consider resampling it beforehand.",
+                    sample_rate, device_rate
+                );
+            }
+        }
+    }
+
+    /// Returns the changes in the device's connection since the last poll. Call this regularly, e.g. once per
+    /// frame, to react to disconnects. Without extension ``ALC_EXT_disconnect``, no events are ever returned.
+    pub fn poll_device_events(&self) -> Vec<DeviceEvent> {
+        let connected = match self.inner.device.is_connected() {
+            Ok(connected) => connected,
+            Err(_) => return Vec::new(),
+        };
+
+        if connected == self.inner.connected.replace(connected) {
+            Vec::new()
+        } else if connected {
+            vec![DeviceEvent::Reconnected]
+        } else {
+            vec![DeviceEvent::Disconnected]
+        }
+    }
+
+    pub(crate) fn device(&self) -> &Device {
+        &self.inner.device
+    }
+
+    pub(crate) fn efx(&self) -> AllenResult<&Efx> {
+        self.inner
+            .efx
+            .as_ref()
+            .ok_or_else(|| AllenError::MissingExtension("ALC_EXT_EFX".to_string()))
+    }
+
+    /// The vector pointing from the listener to `source`. Its magnitude is the distance between the two.
+    /// Sources with `AL_SOURCE_RELATIVE` set are already positioned relative to the listener.
+    pub fn listener_to_source_vector(&self, source: &Source) -> AllenResult<[f32; 3]> {
+        let source_position = source.position()?;
+
+        if source.is_relative()? {
+            return Ok(source_position);
+        }
+
+        let listener_position = self.listener().position()?;
+
+        Ok([
+            source_position[0] - listener_position[0],
+            source_position[1] - listener_position[1],
+            source_position[2] - listener_position[2],
+        ])
+    }
+
+    /// Reads back the attributes the driver gave this context, which may differ from the requested ones.
+    pub fn actual_attributes(&self) -> AllenResult<ContextAttributes> {
+        let device = self.inner.device.inner.handle;
+        let _lock = self.make_current();
+
+        let mut size = 0;
+        unsafe { alcGetIntegerv(device, ALC_ATTRIBUTES_SIZE, 1, &mut size) };
+        self.inner.device.check_alc_error()?;
+
+        let mut list = vec![0; size.max(0) as usize];
+        unsafe { alcGetIntegerv(device, ALC_ALL_ATTRIBUTES, size, list.as_mut_ptr()) };
+        self.inner.device.check_alc_error()?;
+
+        let mut attributes = ContextAttributes::default();
+        // Zero-terminated list of key/value pairs.
+        for pair in list.chunks_exact(2).take_while(|pair| pair[0] != 0) {
+            let value = pair[1];
+            match pair[0] {
+                ALC_FREQUENCY => attributes.frequency = value,
+                ALC_REFRESH => attributes.refresh = value,
+                ALC_SYNC => attributes.sync = value != 0,
+                ALC_MONO_SOURCES => attributes.mono_sources = value,
+                ALC_STEREO_SOURCES => attributes.stereo_sources = value,
+                ALC_MAX_AUXILIARY_SENDS => attributes.max_auxiliary_sends = value,
+                _ => {}
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    /// The number of sources that can exist at once, found by generating sources until the driver refuses (capped at
+    /// 4096), then deleting them again. Sources alive during the probe aren't counted. The result is cached.
+    pub fn probe_max_sources(&self) -> AllenResult<usize> {
+        if let Some(max_sources) = self.inner.max_sources.get() {
+            return Ok(max_sources);
+        }
+
+        let _lock = self.make_current();
+
+        let mut handles = Vec::new();
+        while handles.len() < MAX_PROBED_SOURCES {
+            let mut handle = 0;
+            unsafe { alGenSources(1, &mut handle) };
+            if check_al_error().is_err() {
+                break;
+            }
+            handles.push(handle);
+        }
+
+        unsafe { alDeleteSources(handles.len() as i32, handles.as_ptr()) };
+        check_al_error()?;
+
+        self.inner.max_sources.set(Some(handles.len()));
+
+        Ok(handles.len())
+    }
+
     pub fn suspend(&self) -> AllenResult<()> {
         let _lock = self.make_current();
         unsafe {
@@ -140,6 +762,9 @@ impl Context {
     }
 
     pub fn process(&self) -> AllenResult<()> {
+        #[cfg(test)]
+        self.inner.process_calls.set(self.inner.process_calls.get() + 1);
+
         let _lock = self.make_current();
         unsafe {
             alcProcessContext(self.inner.handle);
@@ -147,4 +772,433 @@ impl Context {
         self.inner.device.check_alc_error()?;
         Ok(())
     }
+
+    #[cfg(test)]
+    pub(crate) fn process_calls(&self) -> usize {
+        self.inner.process_calls.get()
+    }
+
+    /// Starts collecting listener and source changes to be applied together with [`UpdateBatch::commit`].
+    pub fn batch<'a>(&self) -> UpdateBatch<'a> {
+        UpdateBatch::new(self.clone())
+    }
+
+    pub fn atomic_updates(&self) -> bool {
+        self.inner.atomic_updates.get()
+    }
+
+    /// Whether [`UpdateBatch::commit`] suspends the context while applying several changes, so they're all heard
+    /// from the same mix. Off by default.
+    pub fn set_atomic_updates(&self, atomic: bool) {
+        self.inner.atomic_updates.set(atomic);
+    }
+
+    /// A human-readable summary of the device and context capabilities, e.g. to log at startup for bug reports.
+    pub fn capability_report(&self) -> AllenResult<String> {
+        let device = &self.inner.device;
+        let has_alc_extension = |name: &str| {
+            device
+                .is_extension_present(&CString::new(name).unwrap())
+                .unwrap_or(false)
+        };
+        let has_al_extension = |name: &str| {
+            let _lock = self.make_current();
+            crate::is_extension_present(&CString::new(name).unwrap()).unwrap_or(false)
+        };
+
+        let attributes = self.actual_attributes()?;
+
+        let hrtf = if has_alc_extension("ALC_SOFT_HRTF") {
+            if device.get_integer(ALC_HRTF_SOFT)? != 0 {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        } else {
+            "unsupported"
+        };
+
+        let output_mode = if has_alc_extension("ALC_SOFT_output_mode") {
+            match device.get_integer(ALC_OUTPUT_MODE_SOFT)? {
+                ALC_MONO_SOFT => "mono",
+                ALC_STEREO_SOFT => "stereo",
+                ALC_STEREO_BASIC_SOFT => "stereo (basic)",
+                ALC_STEREO_UHJ_SOFT => "stereo (UHJ)",
+                ALC_STEREO_HRTF_SOFT => "stereo (HRTF)",
+                ALC_QUAD_SOFT => "quad",
+                ALC_5POINT1_SOFT => "5.1",
+                ALC_6POINT1_SOFT => "6.1",
+                ALC_7POINT1_SOFT => "7.1",
+                _ => "unknown",
+            }
+        } else {
+            "unknown"
+        };
+
+        let formats: Vec<&str> = [
+            ("8-bit", required_extension(SampleFormat::I8)),
+            ("16-bit", required_extension(SampleFormat::I16)),
+            ("32-bit float", required_extension(SampleFormat::F32)),
+            ("64-bit float", required_extension(SampleFormat::F64)),
+            ("surround", Some("AL_EXT_MCFORMATS")),
+        ]
+        .into_iter()
+        .filter(|(_, extension)| extension.is_none_or(has_al_extension))
+        .map(|(format, _)| format)
+        .collect();
+
+        let mut report = String::new();
+        report.push_str(&format!("Device: {}\n", device.try_device_name()?));
+        report.push_str(&format!(
+            "ALC version: {}.{}\n",
+            device.get_integer(ALC_MAJOR_VERSION)?,
+            device.get_integer(ALC_MINOR_VERSION)?
+        ));
+        report.push_str(&format!("AL version: {}\n", self.version()));
+        report.push_str(&format!("Renderer: {} ({})\n", self.renderer(), self.vendor()));
+        report.push_str(&format!("Frequency: {} Hz\n", attributes.frequency));
+        report.push_str(&format!("HRTF: {}\n", hrtf));
+        report.push_str(&format!("Output mode: {}\n", output_mode));
+        report.push_str(&format!(
+            "Sources: {} mono, {} stereo\n",
+            attributes.mono_sources, attributes.stereo_sources
+        ));
+        report.push_str(&format!("Auxiliary sends: {}\n", attributes.max_auxiliary_sends));
+        report.push_str(&format!("Formats: {}\n", formats.join(", ")));
+
+        Ok(report)
+    }
+
+    /// Suspends the context until the returned guard is dropped, so a batch of updates is applied at once.
+    /// The context is processed again on drop, including when a panic unwinds through the scope.
+    pub fn suspend_guard(&self) -> AllenResult<SuspendGuard> {
+        self.suspend()?;
+        Ok(SuspendGuard {
+            context: self.clone(),
+        })
+    }
+}
+
+/// Keeps a [`Context`] suspended while alive. See [`Context::suspend_guard`].
+pub struct SuspendGuard {
+    context: Context,
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.context.process() {
+            println!("WARNING: Processing suspended context failed! {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context, BufferData, Channels, PropertiesContainer, Samples, SourceState};
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn panic_leaves_no_stale_error() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _lock = context.make_current();
+            // Leaves AL_INVALID_VALUE behind without checking it.
+            unsafe { alSourcef(source.handle(), AL_GAIN, -1.0) };
+            panic!("unchecked AL call");
+        }));
+        assert!(result.is_err());
+
+        source.set_gain(0.5).unwrap();
+    }
+
+    #[test]
+    fn rates_mismatch_8000_on_48000() {
+        assert!(rates_mismatch(8000, 48000, 2.0));
+        assert!(rates_mismatch(48000, 8000, 2.0));
+        assert!(!rates_mismatch(8000, 48000, 6.0));
+        assert!(!rates_mismatch(44100, 48000, 2.0));
+
+        let Some(context) = test_context() else { return };
+        let Ok(context) = context.device().create_context_with_attributes(&[(ALC_FREQUENCY, 48000)]) else {
+            return;
+        };
+        let device_rate = context.device().frequency().unwrap();
+        let buffer = context.new_buffer().unwrap();
+        let samples = [0i16; 64];
+
+        // Off by default.
+        buffer.data(BufferData::I16(&samples), Channels::Mono, 8000).unwrap();
+        assert_eq!(context.rate_mismatch_warnings(), 0);
+
+        context.warn_on_rate_mismatch(2.0);
+        buffer.data(BufferData::I16(&samples), Channels::Mono, device_rate).unwrap();
+        assert_eq!(context.rate_mismatch_warnings(), 0);
+        buffer.data(BufferData::I16(&samples), Channels::Mono, 8000).unwrap();
+        assert_eq!(context.rate_mismatch_warnings(), usize::from(rates_mismatch(8000, device_rate, 2.0)));
+    }
+
+    #[test]
+    fn listener_to_source_vector() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+
+        context.listener().set_position([1.0, 2.0, 3.0]).unwrap();
+        source.set_position([4.0, 6.0, 3.0]).unwrap();
+
+        let vector = context.listener_to_source_vector(&source).unwrap();
+        assert_eq!(vector, [3.0, 4.0, 0.0]);
+        let distance = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((distance - 5.0).abs() < 1e-5);
+
+        source.set_relative(true).unwrap();
+        assert_eq!(context.listener_to_source_vector(&source).unwrap(), [4.0, 6.0, 3.0]);
+    }
+
+    #[test]
+    fn suspend_guard_processes_on_panic() {
+        let Some(context) = test_context() else { return };
+        let listener = context.listener();
+        let before = context.process_calls();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _guard = context.suspend_guard().unwrap();
+            listener.set_gain(0.25).unwrap();
+            panic!("inside suspend scope");
+        }));
+        assert!(result.is_err());
+        assert_eq!(context.process_calls() - before, 1);
+
+        listener.set_gain(0.75).unwrap();
+        assert_eq!(listener.gain().unwrap(), 0.75);
+    }
+
+    #[test]
+    fn effect_slot_registry() {
+        let Some(context) = test_context() else { return };
+        let Ok(cave) = context.effect_slot("cave") else { return };
+
+        let same = context.effect_slot("cave").unwrap();
+        assert!(Arc::ptr_eq(&cave, &same));
+
+        let hall = context.effect_slot("hall").unwrap();
+        assert!(!Arc::ptr_eq(&cave, &hall));
+        assert_ne!(cave.handle(), hall.handle());
+    }
+
+    #[test]
+    fn no_device_events_initially() {
+        let Some(context) = test_context() else { return };
+
+        assert!(context.poll_device_events().is_empty());
+        assert!(context.poll_device_events().is_empty());
+    }
+
+    #[test]
+    fn category_gain() {
+        let Some(context) = test_context() else { return };
+        let music = context.new_source().unwrap();
+        let sfx = context.new_source().unwrap();
+        music.set_category(Some("music")).unwrap();
+        sfx.set_category(Some("sfx")).unwrap();
+        music.set_gain(0.8).unwrap();
+        sfx.set_gain(0.8).unwrap();
+
+        context.set_category_gain("music", 0.5).unwrap();
+
+        assert_eq!(context.category_gain("music"), 0.5);
+        assert_eq!(context.category_gain("sfx"), 1.0);
+        assert!((music.output_gain().unwrap() - 0.4).abs() < 1e-6);
+        assert!((sfx.output_gain().unwrap() - 0.8).abs() < 1e-6);
+
+        // Sources joining later pick up the category's gain.
+        let late = context.new_source().unwrap();
+        late.set_category(Some("music")).unwrap();
+        assert!((late.output_gain().unwrap() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn creation_failure_diagnostics() {
+        let Some(context) = test_context() else { return };
+        let device = context.device();
+
+        let attributes = [(ALC_FREQUENCY, -1), (ALC_MONO_SOURCES, -1)];
+        // Drivers may clamp nonsense instead of failing, in which case there's nothing to check.
+        let Err(err) = device.create_context_with_attributes(&attributes) else { return };
+
+        let AllenError::DeviceError(message) = err else { panic!("unexpected error {:?}", err) };
+        assert!(message.contains(device.device_name()), "{}", message);
+        assert!(message.contains(&format!("{:?}", attributes)), "{}", message);
+    }
+
+    #[test]
+    fn probe_max_sources() {
+        let Some(context) = test_context() else { return };
+        let Ok(context) = context
+            .device()
+            .create_context_with_attributes(&[(ALC_MONO_SOURCES, 8), (ALC_STEREO_SOURCES, 0)])
+        else {
+            return;
+        };
+
+        let max_sources = context.probe_max_sources().unwrap();
+        assert!(max_sources > 0);
+        assert_eq!(context.probe_max_sources().unwrap(), max_sources);
+
+        // Every probed source was deleted again, so all of them can be created.
+        let sources: Vec<_> = (0..max_sources)
+            .map(|_| context.new_source().unwrap())
+            .collect();
+        assert_eq!(sources.len(), max_sources);
+    }
+
+    #[test]
+    fn actual_attributes() {
+        let Some(context) = test_context() else { return };
+        let Ok(context) = context
+            .device()
+            .create_context_with_attributes(&[(ALC_FREQUENCY, 48000), (ALC_MONO_SOURCES, 64)])
+        else {
+            return;
+        };
+
+        let attributes = context.actual_attributes().unwrap();
+        assert!((8000..=192000).contains(&attributes.frequency), "{:?}", attributes);
+        assert!(attributes.refresh > 0, "{:?}", attributes);
+        assert!(attributes.mono_sources > 0, "{:?}", attributes);
+        assert!(attributes.stereo_sources >= 0, "{:?}", attributes);
+        assert!(attributes.max_auxiliary_sends >= 0, "{:?}", attributes);
+    }
+
+    #[test]
+    fn reset_audio_state() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let sources: Vec<_> = (0..2).map(|_| context.new_source().unwrap()).collect();
+        for source in &sources {
+            source.set_buffer(Some(&buffer)).unwrap();
+            source.play().unwrap();
+        }
+        let listener = context.listener();
+        listener.set_position([3.0, 1.0, -2.0]).unwrap();
+        listener.set_orientation(Orientation { at: [1.0, 0.0, 0.0], up: [0.0, 0.0, 1.0] }).unwrap();
+
+        context.reset_audio_state().unwrap();
+
+        assert_eq!(listener.position().unwrap(), [0.0, 0.0, 0.0]);
+        let Orientation { at, up } = listener.orientation().unwrap();
+        assert_eq!(at, [0.0, 0.0, -1.0]);
+        assert_eq!(up, [0.0, 1.0, 0.0]);
+        for source in &sources {
+            assert_eq!(source.state().unwrap(), SourceState::Stopped);
+        }
+    }
+
+    #[test]
+    fn capability_report() {
+        let Some(context) = test_context() else { return };
+        let report = context.capability_report().unwrap();
+
+        let device_name = context.device().device_name();
+        assert!(report.contains(&format!("Device: {}", device_name)), "{}", report);
+        assert!(report.lines().any(|line| line.starts_with("AL version: ")), "{}", report);
+        assert!(report.lines().any(|line| line.starts_with("ALC version: ")), "{}", report);
+    }
+
+    #[test]
+    fn scene_round_trip() {
+        let Some(context) = test_context() else { return };
+        context.set_track_buffer_content(true);
+        let buffer = test_buffer(&context, 44100);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.set_position([1.0, 2.0, 3.0]).unwrap();
+        source.set_looping(true).unwrap();
+        context.listener().set_position([4.0, 0.0, 0.0]).unwrap();
+
+        let snapshot = context.scene_snapshot().unwrap();
+        assert!(snapshot.sources[0].buffer.is_some());
+
+        source.set_buffer(None).unwrap();
+        source.set_position([0.0, 0.0, 0.0]).unwrap();
+        source.set_looping(false).unwrap();
+        context.listener().set_position([0.0, 0.0, 0.0]).unwrap();
+
+        context.restore_scene(&snapshot).unwrap();
+        assert_eq!(source.position().unwrap(), [1.0, 2.0, 3.0]);
+        assert!(source.is_looping().unwrap());
+        assert_eq!(context.listener().position().unwrap(), [4.0, 0.0, 0.0]);
+        assert_eq!(context.scene_snapshot().unwrap().sources[0].buffer, snapshot.sources[0].buffer);
+
+        // Without tracking, buffers are left out of snapshots.
+        context.set_track_buffer_content(false);
+        let untracked = test_buffer(&context, 100);
+        source.set_buffer(Some(&untracked)).unwrap();
+        assert_eq!(context.scene_snapshot().unwrap().sources[0].buffer, None);
+    }
+
+    #[test]
+    fn scene_restores_base_gain() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        source.set_gain(0.5).unwrap();
+        source.set_group_gain(0.5).unwrap();
+
+        let snapshot = context.scene_snapshot().unwrap();
+        assert_eq!(snapshot.sources[0].gain, 0.5);
+
+        source.set_gain(1.0).unwrap();
+        context.restore_scene(&snapshot).unwrap();
+        assert_eq!(source.gain().unwrap(), 0.5);
+        assert!((source.output_gain().unwrap() - 0.25).abs() < 1e-6);
+
+        // Recomputing keeps the restored gain.
+        source.recompute_gain().unwrap();
+        assert!((source.output_gain().unwrap() - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn scene_keeps_streaming_queue() {
+        let Some(context) = test_context() else { return };
+        context.set_track_buffer_content(true);
+        let buffers = [test_buffer(&context, 441), test_buffer(&context, 441)];
+        let source = context.new_source().unwrap();
+        source.queue_buffers(&[&buffers[0], &buffers[1]]).unwrap();
+
+        let snapshot = context.scene_snapshot().unwrap();
+        assert!(snapshot.sources[0].streaming);
+        assert_eq!(snapshot.sources[0].buffer, None);
+
+        source.set_position([1.0, 0.0, 0.0]).unwrap();
+        context.restore_scene(&snapshot).unwrap();
+        assert_eq!(source.position().unwrap(), [0.0, 0.0, 0.0]);
+        assert_eq!(PropertiesContainer::<i32>::get(&source, AL_BUFFERS_QUEUED).unwrap(), 2);
+    }
+
+    #[test]
+    fn upload_matched_resamples_to_device_rate() {
+        let Some(context) = test_context() else { return };
+        let Ok(context) = context.device().create_context_with_attributes(&[(ALC_FREQUENCY, 44100)]) else {
+            return;
+        };
+        let frequency = context.device().frequency().unwrap();
+
+        let descriptor = BufferDescriptor::new(Samples::I16(vec![0; 22050]), Channels::Mono, 22050);
+        let buffer = context.upload_matched(&descriptor).unwrap();
+        assert_eq!(buffer.frequency().unwrap(), frequency);
+        // One second either way.
+        assert!((buffer.duration().unwrap() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn destroy_keeps_other_thread_context() {
+        let Some(context) = test_context() else { return };
+        let Ok(other) = context.device().create_context() else { return };
+
+        drop(context.make_current());
+        other.destroy().unwrap();
+        assert!(context.is_current());
+    }
 }
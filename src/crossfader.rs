@@ -0,0 +1,90 @@
+use crate::{AllenResult, Buffer, Context, Source};
+use std::time::Duration;
+
+/// Crossfades between buffers using two sources, e.g. for music transitions: the playing buffer fades out while
+/// the next one fades in. Call [`Crossfader::update`] regularly, e.g. once per frame.
+pub struct Crossfader {
+    sources: [Source; 2],
+    /// Index of the source playing (or fading in) the latest buffer.
+    current: usize,
+}
+
+impl Crossfader {
+    pub fn new(context: &Context) -> AllenResult<Self> {
+        Ok(Self {
+            sources: [context.new_source()?, context.new_source()?],
+            current: 0,
+        })
+    }
+
+    /// The source playing the latest buffer passed to [`Crossfader::start`].
+    pub fn current(&self) -> &Source {
+        &self.sources[self.current]
+    }
+
+    /// Starts playing `next`, fading it in over `duration` while the current buffer fades out and stops.
+    /// A crossfade still in progress is cut short: its outgoing source stops right away.
+    pub fn start(&mut self, next: &Buffer, duration: Duration) -> AllenResult<()> {
+        let outgoing = &self.sources[self.current];
+        let incoming = &self.sources[1 - self.current];
+
+        incoming.stop_now()?;
+        incoming.set_buffer(Some(next))?;
+        incoming.play()?;
+        // Set after playing, replacing the source's own anti-click fade-in.
+        incoming.fade_in(duration)?;
+
+        outgoing.fade_to(0.0, duration, true);
+
+        self.current = 1 - self.current;
+
+        Ok(())
+    }
+
+    /// Whether a crossfade is in progress.
+    pub fn is_active(&self) -> bool {
+        self.sources.iter().any(|source| source.is_fading())
+    }
+
+    /// Advances the fades by `dt`, see [`Source::update`].
+    pub fn update(&self, dt: Duration) -> AllenResult<()> {
+        for source in &self.sources {
+            source.update(dt)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context, SourceState};
+
+    #[test]
+    fn crossfade() {
+        let Some(context) = test_context() else { return };
+        let first = test_buffer(&context, 44100 * 2);
+        let second = test_buffer(&context, 44100 * 2);
+        let mut crossfader = Crossfader::new(&context).unwrap();
+
+        crossfader.start(&first, Duration::ZERO).unwrap();
+        crossfader.update(Duration::ZERO).unwrap();
+        let old = crossfader.current;
+
+        crossfader.start(&second, Duration::from_millis(100)).unwrap();
+        assert!(crossfader.is_active());
+        let old = &crossfader.sources[old];
+        let new = crossfader.current();
+
+        crossfader.update(Duration::from_millis(50)).unwrap();
+        assert!((old.output_gain().unwrap() - 0.5).abs() < 0.01);
+        assert!((new.output_gain().unwrap() - 0.5).abs() < 0.01);
+
+        crossfader.update(Duration::from_millis(50)).unwrap();
+        assert!(!crossfader.is_active());
+        assert_eq!(old.state().unwrap(), SourceState::Stopped);
+        assert_eq!(new.state().unwrap(), SourceState::Playing);
+        assert!((new.output_gain().unwrap() - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,594 @@
+use crate::{AllenError, AllenResult, BufferData, Channels, Resampler};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{mem::size_of, ops::Range, time::Duration};
+
+/// Owned samples of a [`BufferDescriptor`]. Multichannel samples are interleaved.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Samples {
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// The type of the samples held by [`Samples`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SampleFormat {
+    I8,
+    I16,
+    F32,
+    F64,
+}
+
+impl SampleFormat {
+    /// The size of one sample in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            SampleFormat::I8 => size_of::<i8>(),
+            SampleFormat::I16 => size_of::<i16>(),
+            SampleFormat::F32 => size_of::<f32>(),
+            SampleFormat::F64 => size_of::<f64>(),
+        }
+    }
+}
+
+/// How [`BufferDescriptor::downmix`] combines the left and right channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DownmixMode {
+    /// The mean of both channels, which can't clip.
+    #[default]
+    Average,
+    /// Both channels added together, louder but clipped to full scale.
+    Sum,
+    /// Only the left channel.
+    Left,
+    /// Only the right channel.
+    Right,
+}
+
+/// A sample type which can be converted to and from normalized floating point, where full scale is ±1.0.
+pub(crate) trait Sample: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+impl Sample for i8 {
+    fn to_f64(self) -> f64 {
+        self as f64 / i8::MAX as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(-1.0, 1.0) * i8::MAX as f64).round() as i8
+    }
+}
+
+impl Sample for i16 {
+    fn to_f64(self) -> f64 {
+        self as f64 / i16::MAX as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(-1.0, 1.0) * i16::MAX as f64).round() as i16
+    }
+}
+
+impl Sample for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl Sample for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+fn convert<T: Sample, U: Sample>(data: &[T]) -> Vec<U> {
+    data.iter().map(|sample| U::from_f64(sample.to_f64())).collect()
+}
+
+/// Combines interleaved stereo frames into mono samples.
+fn downmix<T: Sample>(data: &[T], mode: DownmixMode) -> Vec<T> {
+    data.chunks_exact(2)
+        .map(|frame| match mode {
+            DownmixMode::Average => T::from_f64((frame[0].to_f64() + frame[1].to_f64()) / 2.0),
+            DownmixMode::Sum => T::from_f64((frame[0].to_f64() + frame[1].to_f64()).clamp(-1.0, 1.0)),
+            DownmixMode::Left => frame[0],
+            DownmixMode::Right => frame[1],
+        })
+        .collect()
+}
+
+/// Subtracts the mean of each channel from its samples.
+fn remove_dc_offset<T: Sample>(data: &mut [T], channels: usize) {
+    let frames = data.len() / channels;
+    if frames == 0 {
+        return;
+    }
+
+    for channel in 0..channels {
+        let samples = || data.iter().skip(channel).step_by(channels);
+        let mean = samples().map(|sample| sample.to_f64()).sum::<f64>() / frames as f64;
+        if mean == 0.0 {
+            continue;
+        }
+
+        for sample in data.iter_mut().skip(channel).step_by(channels) {
+            *sample = T::from_f64(sample.to_f64() - mean);
+        }
+    }
+}
+
+/// The frames with at least one sample reaching `threshold`, from the first to the last.
+fn audible_frames<T: Sample>(data: &[T], channels: usize, threshold: f64) -> Range<usize> {
+    let is_audible = |frame: &[T]| frame.iter().any(|sample| sample.to_f64().abs() >= threshold);
+
+    let mut frames = data.chunks_exact(channels);
+    match frames.position(is_audible) {
+        Some(start) => {
+            // Searched from the end, among the frames after `start`.
+            let last = match frames.rev().position(is_audible) {
+                Some(from_end) => data.len() / channels - 1 - from_end,
+                None => start,
+            };
+            start..last + 1
+        }
+        None => 0..0,
+    }
+}
+
+/// Indices of the samples jumping from the previous sample of their channel by more than `threshold`.
+fn detect_clicks<T: Sample>(data: &[T], channels: usize, threshold: f64) -> Vec<usize> {
+    (channels..data.len())
+        .filter(|&index| (data[index].to_f64() - data[index - channels].to_f64()).abs() > threshold)
+        .collect()
+}
+
+/// Multiplies every sample of each interleaved frame by `gain(frame)`.
+fn apply_gain<T: Sample>(data: &mut [T], channels: usize, gain: impl Fn(usize) -> f64) {
+    for (frame, samples) in data.chunks_mut(channels).enumerate() {
+        let gain = gain(frame);
+        if gain == 1.0 {
+            // Leave untouched samples exactly as they were, since integer round trips clip e.g. -32768.
+            continue;
+        }
+
+        for sample in samples {
+            *sample = T::from_f64(sample.to_f64() * gain);
+        }
+    }
+}
+
+/// Evaluates an expression with whichever sample vector is held.
+macro_rules! with_samples {
+    ($samples:expr, |$data:ident| $body:expr) => {
+        match $samples {
+            Samples::I8($data) => $body,
+            Samples::I16($data) => $body,
+            Samples::F32($data) => $body,
+            Samples::F64($data) => $body,
+        }
+    };
+}
+
+/// Applies the same expression to whichever sample vector is held, producing a new [`Samples`] of the same type.
+macro_rules! map_samples {
+    ($samples:expr, |$data:ident| $body:expr) => {
+        match $samples {
+            Samples::I8($data) => Samples::I8($body),
+            Samples::I16($data) => Samples::I16($body),
+            Samples::F32($data) => Samples::F32($body),
+            Samples::F64($data) => Samples::F64($body),
+        }
+    };
+}
+
+impl Samples {
+    /// Decodes big-endian 16-bit samples, e.g. from AIFF files. Fails with [`AllenError::InvalidValue`] on an odd
+    /// number of bytes.
+    pub fn from_be_i16_bytes(bytes: &[u8]) -> AllenResult<Samples> {
+        Self::from_i16_bytes(bytes, i16::from_be_bytes)
+    }
+
+    /// Decodes little-endian 16-bit samples, e.g. from WAV files. Fails with [`AllenError::InvalidValue`] on an odd
+    /// number of bytes.
+    pub fn from_le_i16_bytes(bytes: &[u8]) -> AllenResult<Samples> {
+        Self::from_i16_bytes(bytes, i16::from_le_bytes)
+    }
+
+    fn from_i16_bytes(bytes: &[u8], decode: fn([u8; 2]) -> i16) -> AllenResult<Samples> {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(AllenError::InvalidValue);
+        }
+
+        Ok(Samples::I16(
+            bytes
+                .chunks_exact(2)
+                .map(|sample| decode([sample[0], sample[1]]))
+                .collect(),
+        ))
+    }
+
+    /// The number of samples (not frames).
+    pub fn len(&self) -> usize {
+        with_samples!(self, |data| data.len())
+    }
+
+    pub fn format(&self) -> SampleFormat {
+        match self {
+            Samples::I8(_) => SampleFormat::I8,
+            Samples::I16(_) => SampleFormat::I16,
+            Samples::F32(_) => SampleFormat::F32,
+            Samples::F64(_) => SampleFormat::F64,
+        }
+    }
+
+    /// Converts the samples to another format. Integer full scale (e.g. ±32767 for i16) maps to ±1.0 in floating
+    /// point; floating point samples outside of ±1.0 are clipped when converted to integers.
+    pub fn convert_to(&self, format: SampleFormat) -> Samples {
+        with_samples!(self, |data| match format {
+            SampleFormat::I8 => Samples::I8(convert(data)),
+            SampleFormat::I16 => Samples::I16(convert(data)),
+            SampleFormat::F32 => Samples::F32(convert(data)),
+            SampleFormat::F64 => Samples::F64(convert(data)),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Borrows the samples for [`crate::Buffer::data`].
+    pub fn as_data(&self) -> BufferData<'_> {
+        match self {
+            Samples::I8(data) => BufferData::I8(data),
+            Samples::I16(data) => BufferData::I16(data),
+            Samples::F32(data) => BufferData::F32(data),
+            Samples::F64(data) => BufferData::F64(data),
+        }
+    }
+}
+
+/// Owned audio data along with its layout, so it can be processed before being uploaded with [`crate::Buffer::upload`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BufferDescriptor {
+    pub samples: Samples,
+    pub channels: Channels,
+    pub sample_rate: i32,
+}
+
+impl BufferDescriptor {
+    pub fn new(samples: Samples, channels: Channels, sample_rate: i32) -> Self {
+        Self {
+            samples,
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// The number of frames, where one frame holds one sample per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.count()
+    }
+
+    /// Converts the samples to another format, keeping the channels and sample rate. See [`Samples::convert_to`].
+    pub fn convert_to(&self, format: SampleFormat) -> BufferDescriptor {
+        Self {
+            samples: self.samples.convert_to(format),
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Converts the audio to `to_rate` with `resampler`, keeping the format and channels.
+    pub fn resample(&self, to_rate: i32, resampler: &dyn Resampler) -> BufferDescriptor {
+        let input: Vec<f64> = with_samples!(&self.samples, |data| convert(data));
+        let output = resampler.resample(&input, self.channels.count(), self.sample_rate, to_rate);
+
+        Self {
+            samples: Samples::F64(output).convert_to(self.samples.format()),
+            channels: self.channels,
+            sample_rate: to_rate,
+        }
+    }
+
+    /// Mixes stereo audio down to mono with `mode`. Mono audio is returned unchanged.
+    pub fn downmix(&self, mode: DownmixMode) -> BufferDescriptor {
+        match self.channels {
+            Channels::Mono => self.clone(),
+            Channels::Stereo => Self {
+                samples: map_samples!(&self.samples, |data| downmix(data, mode)),
+                channels: Channels::Mono,
+                sample_rate: self.sample_rate,
+            },
+        }
+    }
+
+    /// The length of `duration` in whole frames at this descriptor's sample rate.
+    fn duration_to_frames(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate as f64).round() as usize
+    }
+
+    /// Bakes linear fades into the first `fade_in` and the last `fade_out` of the audio, for soft edges without
+    /// runtime gain automation. Fades longer than the audio are cut short.
+    pub fn apply_fades(&mut self, fade_in: Duration, fade_out: Duration) {
+        let frames = self.frames();
+        let fade_in = self.duration_to_frames(fade_in).min(frames);
+        let fade_out = self.duration_to_frames(fade_out).min(frames);
+        let channels = self.channels.count();
+
+        let gain = |frame: usize| {
+            let mut gain = 1.0;
+            if frame < fade_in {
+                gain *= frame as f64 / fade_in as f64;
+            }
+            let remaining = frames - 1 - frame;
+            if remaining < fade_out {
+                gain *= remaining as f64 / fade_out as f64;
+            }
+            gain
+        };
+
+        with_samples!(&mut self.samples, |data| apply_gain(data, channels, gain));
+    }
+
+    /// Removes any DC bias, e.g. from a capture device, by subtracting each channel's mean. A bias makes playback
+    /// click when it starts and stops.
+    pub fn remove_dc_offset(&mut self) {
+        let channels = self.channels.count();
+        with_samples!(&mut self.samples, |data| remove_dc_offset(data, channels));
+    }
+
+    /// An estimate of the perceived loudness in LUFS-like units, for matching levels across clips. This follows the
+    /// gating of ITU BS.1770 (overlapping 400 ms blocks, absolute gate at -70, relative gate at -10) but skips its
+    /// K-weighting filter, so it's an approximation rather than a compliant measurement. Silence is
+    /// `f32::NEG_INFINITY`.
+    pub fn integrated_loudness(&self) -> f32 {
+        let channels = self.channels.count();
+        let samples: Vec<f64> = with_samples!(&self.samples, |data| convert(data));
+        let frames = samples.len() / channels;
+        if frames == 0 {
+            return f32::NEG_INFINITY;
+        }
+
+        // Blocks of 400 ms, overlapping by 75%. Shorter clips are measured as a single block.
+        let block = self.duration_to_frames(Duration::from_millis(400)).clamp(1, frames);
+        let step = (block / 4).max(1);
+
+        // Mean square of each block, summed over channels.
+        let powers: Vec<f64> = (0..=frames - block)
+            .step_by(step)
+            .map(|start| {
+                let block_samples = &samples[start * channels..(start + block) * channels];
+                block_samples.iter().map(|sample| sample * sample).sum::<f64>() / block as f64
+            })
+            .collect();
+
+        let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+        let gated_mean = |threshold: f64| {
+            let gated: Vec<f64> = powers
+                .iter()
+                .copied()
+                .filter(|power| loudness(*power) > threshold)
+                .collect();
+            (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+        };
+
+        let absolute = match gated_mean(-70.0) {
+            Some(power) => power,
+            None => return f32::NEG_INFINITY,
+        };
+        let relative = gated_mean(loudness(absolute) - 10.0).unwrap_or(absolute);
+
+        loudness(relative) as f32
+    }
+
+    /// Removes the leading and trailing frames whose samples are all below `threshold` (full scale being 1.0), e.g.
+    /// the silence around a recorded one-shot. Returns the range of the original frames that was kept, to shift loop
+    /// points or markers by; audio that's silent throughout is emptied.
+    pub fn trim_silence(&mut self, threshold: f32) -> Range<usize> {
+        let channels = self.channels.count();
+        let kept = with_samples!(&self.samples, |data| audible_frames(data, channels, threshold as f64));
+
+        let samples = kept.start * channels..kept.end * channels;
+        with_samples!(&mut self.samples, |data| {
+            data.truncate(samples.end);
+            data.drain(..samples.start);
+        });
+
+        kept
+    }
+
+    /// Flags likely clicks and pops, e.g. from bad edits, as the indices of the (interleaved) samples which jump from
+    /// the previous sample of their channel by more than `threshold`, with full scale being 1.0.
+    pub fn detect_clicks(&self, threshold: f32) -> Vec<usize> {
+        let channels = self.channels.count();
+        with_samples!(&self.samples, |data| detect_clicks(data, channels, threshold as f64))
+    }
+
+    /// Copies the frames in `start_frame..end_frame` into a new descriptor with the same format and sample rate.
+    pub fn slice(&self, start_frame: usize, end_frame: usize) -> AllenResult<BufferDescriptor> {
+        if start_frame > end_frame || end_frame > self.frames() {
+            return Err(AllenError::InvalidValue);
+        }
+
+        let channels = self.channels.count();
+        let range = start_frame * channels..end_frame * channels;
+
+        Ok(Self {
+            samples: map_samples!(&self.samples, |data| data[range].to_vec()),
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice() {
+        let samples: Vec<i16> = (0..2000).map(|sample| sample as i16).collect();
+        let descriptor = BufferDescriptor::new(Samples::I16(samples), Channels::Stereo, 44100);
+        assert_eq!(descriptor.frames(), 1000);
+
+        let slice = descriptor.slice(100, 200).unwrap();
+        assert_eq!(slice.frames(), 100);
+        assert_eq!(slice.channels, Channels::Stereo);
+        assert_eq!(slice.sample_rate, 44100);
+        match &slice.samples {
+            Samples::I16(data) => {
+                assert_eq!(data.len(), 200);
+                assert_eq!(data[0], 200);
+                assert_eq!(data[199], 399);
+            }
+            samples => panic!("unexpected format {:?}", samples.format()),
+        }
+
+        assert!(descriptor.slice(200, 100).is_err());
+        assert!(descriptor.slice(900, 1001).is_err());
+        assert_eq!(descriptor.slice(1000, 1000).unwrap().frames(), 0);
+    }
+
+    #[test]
+    fn convert_to() {
+        let descriptor = BufferDescriptor::new(Samples::I16(vec![i16::MAX, -i16::MAX, 0]), Channels::Mono, 44100);
+
+        let float = descriptor.convert_to(SampleFormat::F32);
+        assert_eq!(float.sample_rate, 44100);
+        let Samples::F32(data) = &float.samples else { panic!("not converted to f32") };
+        assert!((data[0] - 1.0).abs() < 1e-6);
+        assert!((data[1] + 1.0).abs() < 1e-6);
+        assert_eq!(data[2], 0.0);
+
+        assert_eq!(float.convert_to(SampleFormat::I16), descriptor);
+
+        let Samples::I8(data) = descriptor.convert_to(SampleFormat::I8).samples else { panic!("not converted to i8") };
+        assert_eq!(data, vec![i8::MAX, -i8::MAX, 0]);
+
+        // Out of range floats clip.
+        let loud = Samples::F32(vec![2.0, -2.0]).convert_to(SampleFormat::I16);
+        assert_eq!(loud, Samples::I16(vec![i16::MAX, -i16::MAX]));
+    }
+
+    #[test]
+    fn apply_fades() {
+        // One second of full scale at 1000 Hz.
+        let mut descriptor = BufferDescriptor::new(Samples::F32(vec![1.0; 1000]), Channels::Mono, 1000);
+        descriptor.apply_fades(Duration::from_millis(100), Duration::ZERO);
+
+        let Samples::F32(data) = &descriptor.samples else { panic!("format changed") };
+        assert!(data[0].abs() < 1e-6);
+        assert!((data[50] - 0.5).abs() < 1e-6);
+        assert!(data[..100].windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(data[100..].iter().all(|sample| *sample == 1.0));
+
+        descriptor.apply_fades(Duration::ZERO, Duration::from_millis(100));
+        let Samples::F32(data) = &descriptor.samples else { panic!("format changed") };
+        assert!(data[999].abs() < 1e-6);
+        assert!(data[100..900].iter().all(|sample| *sample == 1.0));
+    }
+
+    #[test]
+    fn from_i16_bytes() {
+        let sample: i16 = -12345;
+        let be = sample.to_be_bytes();
+        let le = sample.to_le_bytes();
+
+        assert_eq!(Samples::from_be_i16_bytes(&[be[0], be[1], 0x01, 0x02]).unwrap(), Samples::I16(vec![sample, 0x0102]));
+        assert_eq!(Samples::from_le_i16_bytes(&[le[0], le[1], 0x02, 0x01]).unwrap(), Samples::I16(vec![sample, 0x0102]));
+
+        assert!(Samples::from_be_i16_bytes(&[0x01, 0x02, 0x03]).is_err());
+    }
+
+    #[test]
+    fn downmix_modes() {
+        let descriptor = BufferDescriptor::new(Samples::F32(vec![0.5, 0.25, 0.75, 0.75]), Channels::Stereo, 44100);
+        let downmixed = |mode| match descriptor.downmix(mode) {
+            BufferDescriptor { samples: Samples::F32(data), channels: Channels::Mono, .. } => data,
+            other => panic!("unexpected downmix {:?}", other.channels),
+        };
+
+        assert_eq!(downmixed(DownmixMode::Average), vec![0.375, 0.75]);
+        assert_eq!(downmixed(DownmixMode::Sum), vec![0.75, 1.0]);
+        assert_eq!(downmixed(DownmixMode::Left), vec![0.5, 0.75]);
+        assert_eq!(downmixed(DownmixMode::Right), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn remove_dc_offset() {
+        // Left is biased by +0.25, right by -0.5.
+        let samples = vec![0.75, -1.0, -0.25, 0.0, 0.25, -0.5];
+        let mut descriptor = BufferDescriptor::new(Samples::F32(samples), Channels::Stereo, 44100);
+        descriptor.remove_dc_offset();
+
+        let Samples::F32(data) = &descriptor.samples else { panic!("format changed") };
+        assert_eq!(data, &[0.5, -0.5, -0.5, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn integrated_loudness() {
+        // One second of a 441 Hz tone, at full and at a quarter amplitude.
+        let tone = |amplitude: f32| -> Vec<f32> {
+            (0..44100)
+                .map(|frame| amplitude * (frame as f32 * 441.0 / 44100.0 * std::f32::consts::TAU).sin())
+                .collect()
+        };
+        let loud = BufferDescriptor::new(Samples::F32(tone(1.0)), Channels::Mono, 44100);
+        let quiet = BufferDescriptor::new(Samples::F32(tone(0.25)), Channels::Mono, 44100);
+
+        let (loud, quiet) = (loud.integrated_loudness(), quiet.integrated_loudness());
+        assert!(loud > quiet, "{} <= {}", loud, quiet);
+        // A quarter of the amplitude is about 12 dB down.
+        assert!((loud - quiet - 12.04).abs() < 0.1, "{} vs {}", loud, quiet);
+
+        let silence = BufferDescriptor::new(Samples::F32(vec![0.0; 44100]), Channels::Mono, 44100);
+        assert_eq!(silence.integrated_loudness(), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn trim_silence() {
+        // Stereo: three silent frames, four frames of audio with a quiet gap, two silent frames.
+        let samples = vec![
+            0.0, 0.0, 0.01, -0.01, 0.0, 0.0, //
+            0.0, 0.5, 0.5, 0.5, 0.0, 0.0, -0.5, 0.0, //
+            0.01, 0.0, 0.0, 0.0,
+        ];
+        let mut descriptor = BufferDescriptor::new(Samples::F32(samples), Channels::Stereo, 44100);
+
+        assert_eq!(descriptor.trim_silence(0.1), 3..7);
+        let Samples::F32(data) = &descriptor.samples else { panic!("format changed") };
+        assert_eq!(data, &[0.0, 0.5, 0.5, 0.5, 0.0, 0.0, -0.5, 0.0]);
+
+        let mut silence = BufferDescriptor::new(Samples::I16(vec![0; 10]), Channels::Mono, 44100);
+        assert_eq!(silence.trim_silence(0.1), 0..0);
+        assert_eq!(silence.frames(), 0);
+    }
+
+    #[test]
+    fn detect_clicks() {
+        // A smooth stereo ramp with a bad edit in the right channel of the fourth frame.
+        let mut samples: Vec<i16> = (0..20).map(|sample| sample * 100).collect();
+        samples[7] = i16::MAX;
+        let descriptor = BufferDescriptor::new(Samples::I16(samples), Channels::Stereo, 44100);
+
+        // The jump into the click and the one back out of it.
+        assert_eq!(descriptor.detect_clicks(0.5), vec![7, 9]);
+        assert!(descriptor.detect_clicks(1.5).is_empty());
+    }
+}
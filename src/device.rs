@@ -1,14 +1,106 @@
 use crate::{AllenError, AllenResult, Context};
-use std::{ffi::CStr, ptr, sync::Arc};
+use std::{
+    ffi::{CStr, CString},
+    ptr,
+    sync::Arc,
+};
 use oal_sys_windows::*;
 
+pub(crate) fn check_alc_error(device: *mut ALCdevice) -> AllenResult<()> {
+    let error = unsafe { alcGetError(device) };
+
+    if error == ALC_NO_ERROR {
+        Ok(())
+    } else {
+        Err(match error {
+            ALC_INVALID_DEVICE => AllenError::InvalidDevice,
+            ALC_INVALID_CONTEXT => AllenError::InvalidContext,
+            ALC_INVALID_ENUM => AllenError::InvalidEnum,
+            ALC_INVALID_VALUE => AllenError::InvalidValue,
+            ALC_OUT_OF_MEMORY => AllenError::OutOfMemory,
+            e => AllenError::Unknown(e),
+        })
+    }
+}
+
+fn alc_get_string_ptr(device: *mut ALCdevice, token: ALCenum) -> AllenResult<*const ALCchar> {
+    let result = unsafe { alcGetString(device, token) };
+
+    if result.is_null() {
+        // A NULL string should always come with an error, but don't rely on it.
+        check_alc_error(device)?;
+        Err(AllenError::InvalidValue)
+    } else {
+        Ok(result)
+    }
+}
+
+/// Reads a single ALC string attribute. `device` may be null for device-independent attributes.
+pub(crate) fn alc_get_string(device: *mut ALCdevice, token: ALCenum) -> AllenResult<String> {
+    let result = alc_get_string_ptr(device, token)?;
+    Ok(unsafe { CStr::from_ptr(result) }
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Reads an ALC string list attribute, such as the device specifiers.
+/// `device` may be null for device-independent attributes.
+pub(crate) fn alc_get_string_list(
+    device: *mut ALCdevice,
+    token: ALCenum,
+) -> AllenResult<Vec<String>> {
+    let result = alc_get_string_ptr(device, token)?;
+    Ok(unsafe { parse_string_list(result) })
+}
+
+/// Splits a list of NUL-separated strings which is terminated by an empty string (i.e. a double NUL).
+///
+/// # Safety
+/// `list` must point to a valid, double NUL-terminated string list.
+pub(crate) unsafe fn parse_string_list(mut list: *const ALCchar) -> Vec<String> {
+    let mut strings = Vec::new();
+
+    loop {
+        let string = CStr::from_ptr(list);
+        let length = string.to_bytes().len();
+
+        if length == 0 {
+            break;
+        }
+
+        strings.push(string.to_string_lossy().into_owned());
+        list = list.add(length + 1);
+    }
+
+    strings
+}
+
 pub(crate) struct DeviceInner {
     pub(crate) handle: *mut ALCdevice,
 }
 
+impl DeviceInner {
+    fn close(&mut self) -> AllenResult<()> {
+        if self.handle.is_null() {
+            return Ok(());
+        }
+
+        // Fails if the device still has contexts.
+        let closed = unsafe { alcCloseDevice(self.handle) } != 0;
+        if !closed {
+            return Err(AllenError::InvalidDevice);
+        }
+        self.handle = ptr::null_mut();
+
+        Ok(())
+    }
+}
+
 impl Drop for DeviceInner {
     fn drop(&mut self) {
-        unsafe { alcCloseDevice(self.handle) };
+        if let Err(err) = self.close() {
+            println!("WARNING: Device drop failed! {}", err);
+        }
     }
 }
 
@@ -33,6 +125,33 @@ impl Device {
         }
     }
 
+    /// Closes the device now instead of on drop, so failures can be observed. Contexts hold on to their device, so
+    /// this fails with [`AllenError::InvalidOperation`] while other handles to it exist, including contexts; the
+    /// device is then closed once the last of them is dropped.
+    pub fn close(self) -> AllenResult<()> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(mut inner) => inner.close(),
+            Err(_) => Err(AllenError::InvalidOperation),
+        }
+    }
+
+    /// Lists the names of the available output devices, which can be passed into [`Device::open`].
+    pub fn enumerate() -> AllenResult<Vec<String>> {
+        let enumerate_all = unsafe {
+            let name = CString::new("ALC_ENUMERATE_ALL_EXT").unwrap();
+            alcIsExtensionPresent(ptr::null_mut(), name.as_ptr()) != 0
+        };
+
+        alc_get_string_list(
+            ptr::null_mut(),
+            if enumerate_all {
+                ALC_ALL_DEVICES_SPECIFIER
+            } else {
+                ALC_DEVICE_SPECIFIER
+            },
+        )
+    }
+
     /// The name of the device.
     pub fn device_name(&self) -> &str {
         unsafe { CStr::from_ptr(alcGetString(self.inner.handle, ALC_DEVICE_SPECIFIER)) }
@@ -40,9 +159,69 @@ impl Device {
             .unwrap()
     }
 
+    /// Like [`Device::device_name`], but fails instead of panicking when the name can't be read.
+    pub(crate) fn try_device_name(&self) -> AllenResult<String> {
+        alc_get_string(self.inner.handle, ALC_DEVICE_SPECIFIER)
+    }
+
+    /// The space-separated list of ALC extensions supported by the device.
+    pub fn extensions(&self) -> AllenResult<String> {
+        alc_get_string(self.inner.handle, ALC_EXTENSIONS)
+    }
+
+    /// The device clock in nanoseconds, which advances with the audio actually mixed.
+    /// Requires extension ``ALC_SOFT_device_clock``.
+    pub fn clock_ns(&self) -> AllenResult<i64> {
+        self.check_alc_extension(&CString::new("ALC_SOFT_device_clock").unwrap())?;
+
+        let function: LPALCGETINTEGER64VSOFT = unsafe {
+            let name = CString::new("alcGetInteger64vSOFT").unwrap();
+
+            std::mem::transmute(alcGetProcAddress(
+                self.inner.handle,
+                name.as_ptr() as *const ALCchar,
+            ))
+        };
+
+        let mut value = 0;
+        unsafe { function.unwrap()(self.inner.handle, ALC_DEVICE_CLOCK_SOFT, 1, &mut value) };
+        self.check_alc_error()?;
+
+        Ok(value)
+    }
+
+    /// The output sample rate of the device.
+    pub fn frequency(&self) -> AllenResult<i32> {
+        self.get_integer(ALC_FREQUENCY)
+    }
+
+    pub(crate) fn get_integer(&self, param: ALCenum) -> AllenResult<i32> {
+        let mut value = 0;
+        unsafe { alcGetIntegerv(self.inner.handle, param, 1, &mut value) };
+        self.check_alc_error()?;
+
+        Ok(value)
+    }
+
+    /// Whether the device is still usable, e.g. hasn't been unplugged. Requires extension ``ALC_EXT_disconnect``.
+    pub fn is_connected(&self) -> AllenResult<bool> {
+        self.check_alc_extension(&CString::new("ALC_EXT_disconnect").unwrap())?;
+
+        let mut value = 0;
+        unsafe { alcGetIntegerv(self.inner.handle, ALC_CONNECTED, 1, &mut value) };
+        self.check_alc_error()?;
+
+        Ok(value != 0)
+    }
+
     /// Creates a context under the device.
     pub fn create_context(&self) -> AllenResult<Context> {
-        Context::new(self.clone())
+        Context::new(self.clone(), &[])
+    }
+
+    /// Creates a context under the device, passing `(ALC_* attribute, value)` pairs to ``alcCreateContext``.
+    pub fn create_context_with_attributes(&self, attributes: &[(i32, i32)]) -> AllenResult<Context> {
+        Context::new(self.clone(), attributes)
     }
 
     pub fn is_extension_present(&self, name: &CStr) -> AllenResult<bool> {
@@ -63,19 +242,37 @@ impl Device {
     }
 
     pub(crate) fn check_alc_error(&self) -> AllenResult<()> {
-        let error = unsafe { alcGetError(self.inner.handle) };
+        check_alc_error(self.inner.handle)
+    }
+}
 
-        if error == ALC_NO_ERROR {
-            Ok(())
-        } else {
-            Err(match error {
-                ALC_INVALID_DEVICE => AllenError::InvalidDevice,
-                ALC_INVALID_CONTEXT => AllenError::InvalidContext,
-                ALC_INVALID_ENUM => AllenError::InvalidEnum,
-                ALC_INVALID_VALUE => AllenError::InvalidValue,
-                ALC_OUT_OF_MEMORY => AllenError::OutOfMemory,
-                e => AllenError::Unknown(e),
-            })
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_string_list_splits_on_double_nul() {
+        let list = b"Speakers\0Headphones\0HDMI\0\0";
+        let strings = unsafe { parse_string_list(list.as_ptr() as *const ALCchar) };
+        assert_eq!(strings, vec!["Speakers", "Headphones", "HDMI"]);
+    }
+
+    #[test]
+    fn parse_string_list_empty() {
+        let list = b"\0\0";
+        let strings = unsafe { parse_string_list(list.as_ptr() as *const ALCchar) };
+        assert!(strings.is_empty());
+    }
+
+    #[test]
+    fn explicit_teardown() {
+        let Some(device) = Device::open(None) else { return };
+        let context = device.create_context().unwrap();
+
+        // The context still holds the device.
+        assert!(matches!(device.clone().close(), Err(AllenError::InvalidOperation)));
+
+        assert!(context.destroy().is_ok());
+        assert!(device.close().is_ok());
     }
 }
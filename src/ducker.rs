@@ -0,0 +1,87 @@
+use crate::{AllenResult, SourceGroup};
+use std::time::Duration;
+
+/// Sidechain ducking between two [`SourceGroup`]s, e.g. music ducking under dialogue: while any source of the
+/// trigger group plays, the target group's gain is lowered. Call [`Ducker::update`] regularly, e.g. once per frame.
+pub struct Ducker {
+    /// The target's gain factor while ducked.
+    ducked_gain: f32,
+    attack: Duration,
+    release: Duration,
+    current: f32,
+}
+
+impl Ducker {
+    /// Ducks down to `ducked_gain` over `attack` once the trigger plays, and back up over `release` after it stops.
+    pub fn new(ducked_gain: f32, attack: Duration, release: Duration) -> Self {
+        Self {
+            ducked_gain,
+            attack,
+            release,
+            current: 1.0,
+        }
+    }
+
+    /// The gain factor currently applied to the target, from `ducked_gain` to 1.0.
+    pub fn current_gain(&self) -> f32 {
+        self.current
+    }
+
+    /// Advances the ducking by `dt`, applying it to `target` depending on whether `trigger` is playing.
+    pub fn update(&mut self, trigger: &SourceGroup, target: &mut SourceGroup, dt: Duration) -> AllenResult<()> {
+        let (goal, time) = if trigger.is_playing()? {
+            (self.ducked_gain, self.attack)
+        } else {
+            (1.0, self.release)
+        };
+
+        let previous = self.current;
+        if time.is_zero() {
+            self.current = goal;
+        } else {
+            // Constant rate, so a full duck takes exactly `time`.
+            let step = (1.0 - self.ducked_gain).abs() * dt.as_secs_f32() / time.as_secs_f32();
+            self.current = if self.current < goal {
+                (self.current + step).min(goal)
+            } else {
+                (self.current - step).max(goal)
+            };
+        }
+
+        if self.current != previous {
+            target.set_duck(self.current)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context};
+
+    #[test]
+    fn voice_ducks_music() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100 * 4);
+        let mut voice = SourceGroup::new();
+        let mut music = SourceGroup::new();
+        voice.add(context.new_source().unwrap()).unwrap().set_buffer(Some(&buffer)).unwrap();
+        music.add(context.new_source().unwrap()).unwrap();
+        let mut ducker = Ducker::new(0.25, Duration::from_millis(100), Duration::from_millis(500));
+        let music_gain = |music: &SourceGroup| music.sources()[0].output_gain().unwrap();
+
+        voice.sources()[0].play().unwrap();
+        ducker.update(&voice, &mut music, Duration::from_millis(50)).unwrap();
+        assert!(music_gain(&music) < 1.0 && music_gain(&music) > 0.25);
+        ducker.update(&voice, &mut music, Duration::from_millis(50)).unwrap();
+        assert!((music_gain(&music) - 0.25).abs() < 1e-5);
+
+        voice.sources()[0].stop().unwrap();
+        ducker.update(&voice, &mut music, Duration::from_millis(250)).unwrap();
+        assert!(music_gain(&music) < 1.0);
+        ducker.update(&voice, &mut music, Duration::from_millis(250)).unwrap();
+        assert!((music_gain(&music) - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{check_al_error, AllenResult, Context, PropertiesContainer};
+use oal_sys_windows::*;
+
+/// An EFX effect (reverb, echo, ...). Requires extension ``ALC_EXT_EFX``.
+/// NOTE: Effects are bound to a context.
+pub struct Effect {
+    handle: u32,
+    context: Context,
+}
+
+impl PropertiesContainer<f32> for Effect {
+    fn get(&self, param: i32) -> AllenResult<f32> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0.0;
+            efx.get_effectf.unwrap()(self.handle, param, &mut value);
+            value
+        };
+
+        check_al_error()?;
+
+        Ok(result)
+    }
+
+    fn set(&self, param: i32, value: f32) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        unsafe { efx.effectf.unwrap()(self.handle, param, value) };
+        check_al_error()?;
+
+        Ok(())
+    }
+}
+
+impl PropertiesContainer<i32> for Effect {
+    fn get(&self, param: i32) -> AllenResult<i32> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0;
+            efx.get_effecti.unwrap()(self.handle, param, &mut value);
+            value
+        };
+
+        check_al_error()?;
+
+        Ok(result)
+    }
+
+    fn set(&self, param: i32, value: i32) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        unsafe { efx.effecti.unwrap()(self.handle, param, value) };
+        check_al_error()?;
+
+        Ok(())
+    }
+}
+
+impl Effect {
+    pub(crate) fn new(context: Context) -> AllenResult<Self> {
+        let efx = context.efx()?;
+
+        let mut handle = 0;
+        unsafe {
+            let _lock = context.make_current();
+            efx.gen_effects.unwrap()(1, &mut handle)
+        };
+
+        check_al_error()?;
+
+        Ok(Self { handle, context })
+    }
+
+    pub(crate) fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    getter_setter!(effect_type, set_effect_type, i32, AL_EFFECT_TYPE);
+
+    /// Reads a float parameter of the effect, such as `AL_REVERB_DECAY_TIME`.
+    pub fn get_f(&self, param: i32) -> AllenResult<f32> {
+        self.get(param)
+    }
+
+    /// Reads an integer parameter of the effect, such as `AL_EFFECT_TYPE` or `AL_REVERB_DECAY_HFLIMIT`.
+    pub fn get_i(&self, param: i32) -> AllenResult<i32> {
+        self.get(param)
+    }
+
+    /// Sets a float parameter of the effect.
+    pub fn set_f(&self, param: i32, value: f32) -> AllenResult<()> {
+        self.set(param, value)
+    }
+
+    /// Sets an integer parameter of the effect.
+    pub fn set_i(&self, param: i32, value: i32) -> AllenResult<()> {
+        self.set(param, value)
+    }
+}
+
+impl Drop for Effect {
+    fn drop(&mut self) {
+        if let Ok(efx) = self.context.efx() {
+            let _lock = self.context.make_current();
+            unsafe { efx.delete_effects.unwrap()(1, &self.handle) }
+        }
+        if let Err(err) = check_al_error() {
+            println!("WARNING: Effect drop failed! {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_context;
+    use oal_sys_windows::*;
+
+    #[test]
+    fn reverb_decay_time_readback() {
+        let Some(context) = test_context() else { return };
+        let Ok(effect) = context.new_effect() else { return };
+
+        effect.set_effect_type(AL_EFFECT_REVERB).unwrap();
+        effect.set_f(AL_REVERB_DECAY_TIME, 3.5).unwrap();
+
+        assert_eq!(effect.get_f(AL_REVERB_DECAY_TIME).unwrap(), 3.5);
+        assert_eq!(effect.get_i(AL_EFFECT_TYPE).unwrap(), AL_EFFECT_REVERB);
+    }
+}
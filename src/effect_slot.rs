@@ -0,0 +1,167 @@
+use crate::{check_al_error, AllenResult, Context, Effect, PropertiesContainer};
+use std::cell::Cell;
+use oal_sys_windows::*;
+
+// AL_SOFT_effect_target, missing from the bindings.
+const AL_EFFECTSLOT_TARGET_SOFT: i32 = 0x199C;
+
+/// An EFX auxiliary effect slot, which applies an [`Effect`] to the sources sending to it.
+/// Requires extension ``ALC_EXT_EFX``.
+/// NOTE: Effect slots are bound to a context.
+pub struct EffectSlot {
+    handle: u32,
+    context: Context,
+    /// The effect last loaded with [`EffectSlot::set_effect`], for [`EffectSlot::refresh`].
+    effect: Cell<i32>,
+}
+
+impl PropertiesContainer<f32> for EffectSlot {
+    fn get(&self, param: i32) -> AllenResult<f32> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0.0;
+            efx.get_auxiliary_effect_slotf.unwrap()(self.handle, param, &mut value);
+            value
+        };
+
+        check_al_error()?;
+
+        Ok(result)
+    }
+
+    fn set(&self, param: i32, value: f32) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        unsafe { efx.auxiliary_effect_slotf.unwrap()(self.handle, param, value) };
+        check_al_error()?;
+
+        Ok(())
+    }
+}
+
+impl PropertiesContainer<i32> for EffectSlot {
+    fn get(&self, param: i32) -> AllenResult<i32> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0;
+            efx.get_auxiliary_effect_sloti.unwrap()(self.handle, param, &mut value);
+            value
+        };
+
+        check_al_error()?;
+
+        Ok(result)
+    }
+
+    fn set(&self, param: i32, value: i32) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        unsafe { efx.auxiliary_effect_sloti.unwrap()(self.handle, param, value) };
+        check_al_error()?;
+
+        Ok(())
+    }
+}
+
+impl EffectSlot {
+    pub(crate) fn new(context: Context) -> AllenResult<Self> {
+        let efx = context.efx()?;
+
+        let mut handle = 0;
+        unsafe {
+            let _lock = context.make_current();
+            efx.gen_auxiliary_effect_slots.unwrap()(1, &mut handle)
+        };
+
+        check_al_error()?;
+
+        Ok(Self {
+            handle,
+            context,
+            effect: Cell::new(AL_EFFECT_NULL),
+        })
+    }
+
+    pub(crate) fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    getter_setter!(gain, set_gain, f32, AL_EFFECTSLOT_GAIN);
+    getter_setter!(is_send_auto, set_send_auto, bool, AL_EFFECTSLOT_AUXILIARY_SEND_AUTO);
+
+    /// Loads `effect` into the slot. The effect's parameters are copied, so later changes to it are not heard
+    /// until it's set again. Passing `None` empties the slot.
+    pub fn set_effect(&self, effect: Option<&Effect>) -> AllenResult<()> {
+        let effect = match effect {
+            Some(effect) => effect.handle() as i32,
+            None => AL_EFFECT_NULL,
+        };
+
+        self.set(AL_EFFECTSLOT_EFFECT, effect)?;
+        self.effect.set(effect);
+
+        Ok(())
+    }
+
+    /// Feeds the slot's output into `target` instead of straight to the output, chaining effects together.
+    /// `None` sends it to the output again. Requires extension ``AL_SOFT_effect_target``.
+    pub fn set_target(&self, target: Option<&EffectSlot>) -> AllenResult<()> {
+        crate::check_al_extension(&std::ffi::CString::new("AL_SOFT_effect_target").unwrap())?;
+
+        let target = target.map_or(AL_EFFECTSLOT_NULL, |target| target.handle as i32);
+        self.set(AL_EFFECTSLOT_TARGET_SOFT, target)
+    }
+
+    /// Loads the last effect set with [`EffectSlot::set_effect`] again, so changes made to its parameters since
+    /// are heard.
+    pub fn refresh(&self) -> AllenResult<()> {
+        self.set(AL_EFFECTSLOT_EFFECT, self.effect.get())
+    }
+
+    /// Edits `effect` with `edit`, then loads it into the slot so the changes are heard right away.
+    pub fn update_effect(&self, effect: &Effect, edit: &dyn Fn(&Effect)) -> AllenResult<()> {
+        edit(effect);
+        self.set_effect(Some(effect))
+    }
+}
+
+impl Drop for EffectSlot {
+    fn drop(&mut self) {
+        if let Ok(efx) = self.context.efx() {
+            let _lock = self.context.make_current();
+            unsafe { efx.delete_auxiliary_effect_slots.unwrap()(1, &self.handle) }
+        }
+        if let Err(err) = check_al_error() {
+            println!("WARNING: EffectSlot drop failed! {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_context;
+    use oal_sys_windows::*;
+
+    #[test]
+    fn refresh_after_edit() {
+        let Some(context) = test_context() else { return };
+        let (Ok(slot), Ok(effect)) = (context.new_effect_slot(), context.new_effect()) else { return };
+        effect.set_effect_type(AL_EFFECT_REVERB).unwrap();
+        slot.set_effect(Some(&effect)).unwrap();
+
+        effect.set_f(AL_REVERB_DECAY_TIME, 5.0).unwrap();
+        slot.refresh().unwrap();
+
+        slot.update_effect(&effect, &|effect| {
+            effect.set_f(AL_REVERB_DECAY_TIME, 2.0).unwrap()
+        })
+        .unwrap();
+        assert_eq!(effect.get_f(AL_REVERB_DECAY_TIME).unwrap(), 2.0);
+    }
+}
@@ -0,0 +1,58 @@
+use std::ffi::CString;
+use oal_sys_windows::*;
+
+/// Loads an extension function through `alGetProcAddress`.
+unsafe fn load<T: Copy>(name: &str) -> T {
+    let name = CString::new(name).unwrap();
+    let function = alGetProcAddress(name.as_ptr());
+    std::mem::transmute_copy(&function)
+}
+
+/// Function table for `ALC_EXT_EFX`. EFX entry points are not exported directly and have to be queried at runtime.
+pub(crate) struct Efx {
+    pub(crate) gen_effects: LPALGENEFFECTS,
+    pub(crate) delete_effects: LPALDELETEEFFECTS,
+    pub(crate) effecti: LPALEFFECTI,
+    pub(crate) effectf: LPALEFFECTF,
+    pub(crate) get_effecti: LPALGETEFFECTI,
+    pub(crate) get_effectf: LPALGETEFFECTF,
+    pub(crate) gen_auxiliary_effect_slots: LPALGENAUXILIARYEFFECTSLOTS,
+    pub(crate) delete_auxiliary_effect_slots: LPALDELETEAUXILIARYEFFECTSLOTS,
+    pub(crate) auxiliary_effect_sloti: LPALAUXILIARYEFFECTSLOTI,
+    pub(crate) auxiliary_effect_slotf: LPALAUXILIARYEFFECTSLOTF,
+    pub(crate) get_auxiliary_effect_sloti: LPALGETAUXILIARYEFFECTSLOTI,
+    pub(crate) get_auxiliary_effect_slotf: LPALGETAUXILIARYEFFECTSLOTF,
+    pub(crate) gen_filters: LPALGENFILTERS,
+    pub(crate) delete_filters: LPALDELETEFILTERS,
+    pub(crate) filteri: LPALFILTERI,
+    pub(crate) filterf: LPALFILTERF,
+    pub(crate) get_filteri: LPALGETFILTERI,
+    pub(crate) get_filterf: LPALGETFILTERF,
+}
+
+impl Efx {
+    pub(crate) fn load() -> Self {
+        unsafe {
+            Self {
+                gen_effects: load("alGenEffects"),
+                delete_effects: load("alDeleteEffects"),
+                effecti: load("alEffecti"),
+                effectf: load("alEffectf"),
+                get_effecti: load("alGetEffecti"),
+                get_effectf: load("alGetEffectf"),
+                gen_auxiliary_effect_slots: load("alGenAuxiliaryEffectSlots"),
+                delete_auxiliary_effect_slots: load("alDeleteAuxiliaryEffectSlots"),
+                auxiliary_effect_sloti: load("alAuxiliaryEffectSloti"),
+                auxiliary_effect_slotf: load("alAuxiliaryEffectSlotf"),
+                get_auxiliary_effect_sloti: load("alGetAuxiliaryEffectSloti"),
+                get_auxiliary_effect_slotf: load("alGetAuxiliaryEffectSlotf"),
+                gen_filters: load("alGenFilters"),
+                delete_filters: load("alDeleteFilters"),
+                filteri: load("alFilteri"),
+                filterf: load("alFilterf"),
+                get_filteri: load("alGetFilteri"),
+                get_filterf: load("alGetFilterf"),
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{check_al_error, AllenResult, Context, PropertiesContainer};
+use oal_sys_windows::*;
+
+/// An EFX filter (low-pass, high-pass, band-pass), applied to a source's direct path or auxiliary sends.
+/// Requires extension ``ALC_EXT_EFX``.
+/// NOTE: Filters are bound to a context.
+pub struct Filter {
+    handle: u32,
+    context: Context,
+}
+
+impl PropertiesContainer<f32> for Filter {
+    fn get(&self, param: i32) -> AllenResult<f32> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0.0;
+            efx.get_filterf.unwrap()(self.handle, param, &mut value);
+            value
+        };
+
+        check_al_error()?;
+
+        Ok(result)
+    }
+
+    fn set(&self, param: i32, value: f32) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        unsafe { efx.filterf.unwrap()(self.handle, param, value) };
+        check_al_error()?;
+
+        Ok(())
+    }
+}
+
+impl PropertiesContainer<i32> for Filter {
+    fn get(&self, param: i32) -> AllenResult<i32> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0;
+            efx.get_filteri.unwrap()(self.handle, param, &mut value);
+            value
+        };
+
+        check_al_error()?;
+
+        Ok(result)
+    }
+
+    fn set(&self, param: i32, value: i32) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+        let _lock = self.context.make_current();
+
+        unsafe { efx.filteri.unwrap()(self.handle, param, value) };
+        check_al_error()?;
+
+        Ok(())
+    }
+}
+
+impl Filter {
+    pub(crate) fn new(context: Context) -> AllenResult<Self> {
+        let efx = context.efx()?;
+
+        let mut handle = 0;
+        unsafe {
+            let _lock = context.make_current();
+            efx.gen_filters.unwrap()(1, &mut handle)
+        };
+
+        check_al_error()?;
+
+        Ok(Self { handle, context })
+    }
+
+    pub(crate) fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    getter_setter!(filter_type, set_filter_type, i32, AL_FILTER_TYPE);
+
+    /// Reads a float parameter of the filter, such as `AL_LOWPASS_GAINHF`.
+    pub fn get_f(&self, param: i32) -> AllenResult<f32> {
+        self.get(param)
+    }
+
+    /// Reads an integer parameter of the filter, such as `AL_FILTER_TYPE`.
+    pub fn get_i(&self, param: i32) -> AllenResult<i32> {
+        self.get(param)
+    }
+
+    /// Sets a float parameter of the filter.
+    pub fn set_f(&self, param: i32, value: f32) -> AllenResult<()> {
+        self.set(param, value)
+    }
+
+    /// Sets an integer parameter of the filter.
+    pub fn set_i(&self, param: i32, value: i32) -> AllenResult<()> {
+        self.set(param, value)
+    }
+}
+
+impl Drop for Filter {
+    fn drop(&mut self) {
+        if let Ok(efx) = self.context.efx() {
+            let _lock = self.context.make_current();
+            unsafe { efx.delete_filters.unwrap()(1, &self.handle) }
+        }
+        if let Err(err) = check_al_error() {
+            println!("WARNING: Filter drop failed! {}", err);
+        }
+    }
+}
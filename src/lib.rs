@@ -1,17 +1,55 @@
+mod audio_thread;
+mod batch;
 mod buffer;
+mod buffer_manager;
+mod capture;
 mod context;
+mod crossfader;
+mod descriptor;
 mod device;
+mod ducker;
 #[macro_use]
 mod properties;
+mod effect;
+mod effect_slot;
+mod efx;
+mod filter;
 mod listener;
+mod playlist;
+mod ramp;
+mod rapid_fire;
+mod resampler;
+mod scene;
 mod source;
+mod source_group;
+mod source_pool;
+mod spatial_lod;
 
+pub use audio_thread::*;
+pub use batch::*;
 pub use buffer::*;
+pub use buffer_manager::*;
+pub use capture::*;
 pub use context::*;
+pub use crossfader::*;
+pub use descriptor::*;
 pub use device::*;
+pub use ducker::*;
+pub use effect::*;
+pub use effect_slot::*;
+pub(crate) use efx::*;
+pub use filter::*;
 pub use listener::*;
+pub use playlist::*;
 pub(crate) use properties::*;
+pub(crate) use ramp::*;
+pub use rapid_fire::*;
+pub use resampler::*;
+pub use scene::*;
 pub use source::*;
+pub use source_group::*;
+pub use source_pool::*;
+pub use spatial_lod::*;
 use oal_sys_windows::*;
 
 #[cfg(feature = "serde")]
@@ -29,8 +67,9 @@ pub(crate) type Float3 = [f32; 3];
 #[repr(C, packed)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Orientation {
-    pub up: Float3,
+    /// The direction the listener faces. Declared first, since `AL_ORIENTATION` takes the "at" vector first.
     pub at: Float3,
+    pub up: Float3,
 }
 
 /// An OpenAL error.
@@ -53,8 +92,17 @@ pub enum AllenError {
     #[error("unknown OpenAL error: `{0}`")]
     Unknown(i32),
 
+    #[error("device error: {0}")]
+    DeviceError(String),
+
     #[error("missing OpenAL extension: {0}")]
     MissingExtension(String),
+    #[error("no more sources can be created")]
+    SourceLimitReached,
+    #[error("float sample `{0}` is outside of -1.0..=1.0")]
+    SampleOutOfRange(f64),
+    #[error("{samples} samples don't make whole frames of {channels} channels")]
+    ChannelMismatch { samples: usize, channels: usize },
 }
 
 pub(crate) type AllenResult<T> = Result<T, AllenError>;
@@ -98,3 +146,21 @@ pub(crate) fn check_al_extension(name: &CStr) -> AllenResult<()> {
         ))
     }
 }
+
+/// A context on the default device for tests, or `None` on machines without one, in which case the test should
+/// return early.
+#[cfg(test)]
+pub(crate) fn test_context() -> Option<Context> {
+    Device::open(None)?.create_context().ok()
+}
+
+/// A quiet mono 16-bit buffer of `frames` frames at 44100 Hz for tests.
+#[cfg(test)]
+pub(crate) fn test_buffer(context: &Context, frames: usize) -> Buffer {
+    let samples: Vec<i16> = (0..frames).map(|frame| ((frame % 100) as i16 - 50) * 10).collect();
+    let buffer = context.new_buffer().unwrap();
+    buffer
+        .data(BufferData::I16(&samples), Channels::Mono, 44100)
+        .unwrap();
+    buffer
+}
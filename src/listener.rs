@@ -1,5 +1,5 @@
 use crate::{
-    check_al_error, AllenResult, Context, Float3, Orientation, PropertiesContainer,
+    check_al_error, AllenError, AllenResult, Context, Float3, Orientation, PropertiesContainer,
 };
 use oal_sys_windows::*;
 
@@ -90,4 +90,41 @@ impl Listener {
     getter_setter!(position, set_position, Float3, AL_POSITION);
     getter_setter!(velocity, set_velocity, Float3, AL_VELOCITY);
     getter_setter!(orientation, set_orientation, Orientation, AL_ORIENTATION);
+
+    /// Sets the position to `cur` and derives the velocity from the motion since `prev`, `dt` seconds ago,
+    /// which keeps the Doppler effect correct for a moving listener. `dt` must be positive.
+    pub fn set_velocity_from_motion(&self, prev: Float3, cur: Float3, dt: f32) -> AllenResult<()> {
+        if dt <= 0.0 {
+            return Err(AllenError::InvalidValue);
+        }
+
+        self.set_position(cur)?;
+        self.set_velocity([
+            (cur[0] - prev[0]) / dt,
+            (cur[1] - prev[1]) / dt,
+            (cur[2] - prev[2]) / dt,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_context, AllenError};
+
+    #[test]
+    fn velocity_from_motion() {
+        let Some(context) = test_context() else { return };
+        let listener = context.listener();
+
+        listener
+            .set_velocity_from_motion([0.0, 0.0, 0.0], [1.0, 0.0, -2.0], 0.5)
+            .unwrap();
+        assert_eq!(listener.position().unwrap(), [1.0, 0.0, -2.0]);
+        assert_eq!(listener.velocity().unwrap(), [2.0, 0.0, -4.0]);
+
+        assert!(matches!(
+            listener.set_velocity_from_motion([0.0; 3], [1.0; 3], 0.0),
+            Err(AllenError::InvalidValue)
+        ));
+    }
 }
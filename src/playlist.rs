@@ -0,0 +1,115 @@
+use crate::{AllenResult, Buffer, Source};
+use std::collections::VecDeque;
+
+/// Number of tracks kept queued on the source, so the next one is always ready before the current one ends.
+const QUEUED_TRACKS: usize = 2;
+
+/// Plays a sequence of buffers back-to-back without gaps, by queueing the next track on a streaming source
+/// before the current one finishes. Call [`Playlist::pump`] regularly, e.g. once per frame.
+pub struct Playlist {
+    // Declared first so the source is deleted before the buffers queued on it.
+    source: Source,
+    tracks: Vec<Buffer>,
+    current: usize,
+    /// Index of the next track to queue.
+    next: usize,
+    on_track_change: Option<Box<dyn FnMut(usize)>>,
+}
+
+impl Playlist {
+    /// Takes over `source`, replacing whatever buffer it had with the start of `tracks`.
+    pub fn new(source: Source, tracks: VecDeque<Buffer>) -> AllenResult<Self> {
+        source.set_buffer(None)?;
+
+        let mut playlist = Self {
+            source,
+            tracks: tracks.into(),
+            current: 0,
+            next: 0,
+            on_track_change: None,
+        };
+        playlist.fill_queue()?;
+
+        Ok(playlist)
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    pub fn play(&self) -> AllenResult<()> {
+        self.source.play()
+    }
+
+    /// The index of the track playing. Equal to the track count once the playlist is over.
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Calls `callback` with the new track index whenever playback moves on to the next track.
+    pub fn on_track_change(&mut self, callback: impl FnMut(usize) + 'static) {
+        self.on_track_change = Some(Box::new(callback));
+    }
+
+    /// Retires finished tracks and queues upcoming ones.
+    pub fn pump(&mut self) -> AllenResult<()> {
+        let processed = self.source.buffers_processed()?;
+        if processed > 0 {
+            self.source.unqueue_buffers(processed)?;
+
+            for _ in 0..processed {
+                self.current += 1;
+                if self.current < self.tracks.len() {
+                    if let Some(callback) = &mut self.on_track_change {
+                        callback(self.current);
+                    }
+                }
+            }
+        }
+
+        self.fill_queue()
+    }
+
+    fn fill_queue(&mut self) -> AllenResult<()> {
+        while self.next < self.tracks.len() && self.next < self.current + QUEUED_TRACKS {
+            self.source.queue_buffer(&self.tracks[self.next])?;
+            self.next += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context, SourceState};
+    use std::{cell::RefCell, rc::Rc, thread, time::Duration};
+
+    #[test]
+    fn plays_tracks_in_order() {
+        let Some(context) = test_context() else { return };
+        // 30 ms each.
+        let tracks = (0..3).map(|_| test_buffer(&context, 1323)).collect();
+        let mut playlist = Playlist::new(context.new_source().unwrap(), tracks).unwrap();
+
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let recorded = changes.clone();
+        playlist.on_track_change(move |index| recorded.borrow_mut().push(index));
+
+        playlist.play().unwrap();
+        assert_eq!(playlist.current_index(), 0);
+
+        for _ in 0..500 {
+            if playlist.current_index() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(2));
+            playlist.pump().unwrap();
+        }
+
+        assert_eq!(playlist.current_index(), 3);
+        assert_eq!(*changes.borrow(), vec![1, 2]);
+        assert_eq!(playlist.source().state().unwrap(), SourceState::Stopped);
+    }
+}
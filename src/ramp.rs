@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+/// A linear gain ramp, advanced by [`crate::Source::update`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GainRamp {
+    pub(crate) from: f32,
+    pub(crate) to: f32,
+    pub(crate) elapsed: Duration,
+    pub(crate) duration: Duration,
+    /// Stop the source once the ramp has finished (used for fade-outs).
+    pub(crate) stop_when_done: bool,
+}
+
+impl GainRamp {
+    pub(crate) fn new(from: f32, to: f32, duration: Duration, stop_when_done: bool) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: Duration::ZERO,
+            duration,
+            stop_when_done,
+        }
+    }
+
+    pub(crate) fn advance(&mut self, dt: Duration) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    pub(crate) fn value(&self) -> f32 {
+        if self.duration.is_zero() {
+            return self.to;
+        }
+
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        self.from + (self.to - self.from) * t
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Piecewise-linear gain automation, advanced by [`crate::Source::update`].
+#[derive(Debug, Clone)]
+pub(crate) struct GainCurve {
+    /// Sorted by time.
+    keyframes: Vec<(Duration, f32)>,
+    time: Duration,
+    looping: bool,
+}
+
+impl GainCurve {
+    pub(crate) fn new(keyframes: &[(Duration, f32)], looping: bool) -> Self {
+        let mut keyframes = keyframes.to_vec();
+        keyframes.sort_by_key(|(time, _)| *time);
+
+        Self {
+            keyframes,
+            time: Duration::ZERO,
+            looping,
+        }
+    }
+
+    pub(crate) fn advance(&mut self, dt: Duration) {
+        self.time += dt;
+
+        let length = self.length();
+        if self.looping && !length.is_zero() && self.time >= length {
+            self.time = Duration::from_secs_f64(self.time.as_secs_f64() % length.as_secs_f64());
+        }
+    }
+
+    fn length(&self) -> Duration {
+        self.keyframes
+            .last()
+            .map(|(time, _)| *time)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// The gain at the current time. Before the first and after the last keyframe, the gain holds steady.
+    pub(crate) fn value(&self) -> Option<f32> {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|(time, _)| *time > self.time);
+
+        match next {
+            Some(0) => self.keyframes.first().map(|(_, gain)| *gain),
+            Some(index) => {
+                let (start_time, start_gain) = self.keyframes[index - 1];
+                let (end_time, end_gain) = self.keyframes[index];
+                let t = (self.time - start_time).as_secs_f32() / (end_time - start_time).as_secs_f32();
+                Some(start_gain + (end_gain - start_gain) * t)
+            }
+            None => self.keyframes.last().map(|(_, gain)| *gain),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{AllenError, AllenResult, Buffer, Context, Source, SourceState};
+use std::{cell::Cell, time::Duration};
+
+/// Plays rapid, overlapping triggers of a sound (e.g. gunfire) on a small pool of voices, so a new trigger
+/// doesn't cut off the tail of the previous one. Voices are used round-robin; once all of them are busy, the
+/// oldest one is restarted.
+pub struct RapidFireSource {
+    voices: Vec<Source>,
+    /// Index of the voice to try first on the next trigger.
+    next: Cell<usize>,
+}
+
+impl RapidFireSource {
+    /// Creates a pool of `voices` sources. Fails with [`AllenError::InvalidValue`] if `voices` is zero.
+    pub fn new(context: &Context, voices: usize) -> AllenResult<Self> {
+        if voices == 0 {
+            return Err(AllenError::InvalidValue);
+        }
+
+        Ok(Self {
+            voices: (0..voices)
+                .map(|_| context.new_source())
+                .collect::<AllenResult<_>>()?,
+            next: Cell::new(0),
+        })
+    }
+
+    /// The pool's sources, e.g. to position them.
+    pub fn voices(&self) -> &[Source] {
+        &self.voices
+    }
+
+    /// Plays `buffer` at `gain` on a fresh voice, returning it.
+    pub fn trigger(&self, buffer: &Buffer, gain: f32) -> AllenResult<&Source> {
+        let start = self.next.get();
+        let mut index = start;
+
+        // Prefer a voice which has finished, starting from the oldest trigger.
+        for offset in 0..self.voices.len() {
+            let candidate = (start + offset) % self.voices.len();
+            if self.voices[candidate].state()? != SourceState::Playing {
+                index = candidate;
+                break;
+            }
+        }
+
+        let voice = &self.voices[index];
+        voice.stop_now()?;
+        voice.set_buffer(Some(buffer))?;
+        voice.set_gain(gain)?;
+        voice.play()?;
+
+        self.next.set((index + 1) % self.voices.len());
+
+        Ok(voice)
+    }
+
+    /// Advances the voices' fades, see [`Source::update`].
+    pub fn update(&self, dt: Duration) -> AllenResult<()> {
+        for voice in &self.voices {
+            voice.update(dt)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context};
+
+    #[test]
+    fn triggers_layer_on_distinct_voices() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let rapid_fire = RapidFireSource::new(&context, 4).unwrap();
+
+        let handles: Vec<u32> = (0..3)
+            .map(|_| rapid_fire.trigger(&buffer, 0.5).unwrap().handle())
+            .collect();
+        assert!(handles[0] != handles[1] && handles[1] != handles[2] && handles[0] != handles[2]);
+
+        let playing = rapid_fire
+            .voices()
+            .iter()
+            .filter(|voice| voice.state().unwrap() == SourceState::Playing)
+            .count();
+        assert_eq!(playing, 3);
+
+        assert!(RapidFireSource::new(&context, 0).is_err());
+    }
+}
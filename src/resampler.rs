@@ -0,0 +1,81 @@
+/// Converts audio between sample rates, see [`crate::BufferDescriptor::resample`]. Implement it to plug in a
+/// higher quality resampler, e.g. a sinc resampler from another crate.
+pub trait Resampler {
+    /// Resamples interleaved `input` with `channels` channels from `from_rate` to `to_rate`. Samples are
+    /// normalized, with full scale at ±1.0.
+    fn resample(&self, input: &[f64], channels: usize, from_rate: i32, to_rate: i32) -> Vec<f64>;
+}
+
+/// Resamples by linearly interpolating between neighbouring frames. Fast, but aliases when downsampling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearResampler;
+
+impl Resampler for LinearResampler {
+    fn resample(&self, input: &[f64], channels: usize, from_rate: i32, to_rate: i32) -> Vec<f64> {
+        let frames = input.len() / channels;
+        if frames == 0 || from_rate == to_rate {
+            return input[..frames * channels].to_vec();
+        }
+        if from_rate <= 0 || to_rate <= 0 {
+            return Vec::new();
+        }
+
+        let step = from_rate as f64 / to_rate as f64;
+        let output_frames = (frames as f64 / step).round() as usize;
+
+        let mut output = Vec::with_capacity(output_frames * channels);
+        for frame in 0..output_frames {
+            let position = frame as f64 * step;
+            let index = (position as usize).min(frames - 1);
+            let next = (index + 1).min(frames - 1);
+            let t = position - index as f64;
+
+            for channel in 0..channels {
+                let a = input[index * channels + channel];
+                let b = input[next * channels + channel];
+                output.push(a + (b - a) * t);
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BufferDescriptor, Channels, Samples};
+    use std::f64::consts::TAU;
+
+    fn tone(frequency: f64, rate: i32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|frame| (TAU * frequency * frame as f64 / rate as f64).sin() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn linear_upsampling_preserves_tone() {
+        let descriptor = BufferDescriptor::new(Samples::F32(tone(440.0, 24000, 2400)), Channels::Mono, 24000);
+
+        let resampled = descriptor.resample(48000, &LinearResampler);
+        assert_eq!(resampled.sample_rate, 48000);
+        assert_eq!(resampled.frames(), 4800);
+
+        let Samples::F32(data) = &resampled.samples else { panic!("format changed") };
+        let expected = tone(440.0, 48000, 4800);
+        // The last frame has no neighbour to interpolate towards.
+        let error = data[..4798]
+            .iter()
+            .zip(&expected)
+            .map(|(sample, expected)| (sample - expected).abs())
+            .fold(0.0, f32::max);
+        assert!(error < 0.01, "max error {}", error);
+    }
+
+    #[test]
+    fn linear_stereo_keeps_channels_apart() {
+        let input = [0.0, 1.0, 0.5, -1.0];
+        let output = LinearResampler.resample(&input, 2, 1, 2);
+        assert_eq!(output, vec![0.0, 1.0, 0.25, 0.0, 0.5, -1.0, 0.5, -1.0]);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{check_al_error, AllenResult, Float3, Orientation, SourceState};
+use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use oal_sys_windows::*;
+
+/// The listener's state within a [`SceneSnapshot`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ListenerSnapshot {
+    pub position: Float3,
+    pub velocity: Float3,
+    pub orientation: Orientation,
+    pub gain: f32,
+}
+
+/// A source's state within a [`SceneSnapshot`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceSnapshot {
+    pub position: Float3,
+    pub velocity: Float3,
+    pub direction: Float3,
+    /// The base gain, see [`crate::Source::gain`].
+    pub gain: f32,
+    pub pitch: f32,
+    pub min_gain: f32,
+    pub max_gain: f32,
+    pub reference_distance: f32,
+    pub rolloff_factor: f32,
+    pub max_distance: f32,
+    pub cone_inner_angle: f32,
+    pub cone_outer_angle: f32,
+    pub cone_outer_gain: f32,
+    pub looping: bool,
+    pub relative: bool,
+    /// Content hash of the attached buffer, which must still exist when restoring. `None` if no buffer is attached,
+    /// its content isn't tracked (see [`crate::Context::set_track_buffer_content`]) or the source is streaming.
+    pub buffer: Option<u64>,
+    /// Whether buffers were queued on the source. Queues aren't captured, so neither the buffer nor the playback
+    /// state of a streaming source is restored.
+    pub streaming: bool,
+    pub offset_secs: f32,
+    pub state: SourceState,
+}
+
+/// The listener and every source of a context, taken with [`crate::Context::scene_snapshot`] and reapplied with
+/// [`crate::Context::restore_scene`], e.g. for save states. Buffers are referenced by content, not included.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SceneSnapshot {
+    pub listener: ListenerSnapshot,
+    /// In source creation order.
+    pub sources: Vec<SourceSnapshot>,
+}
+
+/// Reads the listener's state. The context must be locked.
+pub(crate) fn capture_listener() -> AllenResult<ListenerSnapshot> {
+    let mut snapshot = ListenerSnapshot {
+        position: [0.0; 3],
+        velocity: [0.0; 3],
+        orientation: Orientation::default(),
+        gain: 0.0,
+    };
+
+    unsafe {
+        let [x, y, z] = &mut snapshot.position;
+        alGetListener3f(AL_POSITION, x, y, z);
+        let [x, y, z] = &mut snapshot.velocity;
+        alGetListener3f(AL_VELOCITY, x, y, z);
+        alGetListenerfv(AL_ORIENTATION, &mut snapshot.orientation as *mut Orientation as *mut f32);
+        alGetListenerf(AL_GAIN, &mut snapshot.gain);
+    }
+    check_al_error()?;
+
+    Ok(snapshot)
+}
+
+/// Applies a listener's state. The context must be locked.
+pub(crate) fn apply_listener(snapshot: &ListenerSnapshot) -> AllenResult<()> {
+    let [x, y, z] = snapshot.position;
+    let [vx, vy, vz] = snapshot.velocity;
+
+    unsafe {
+        alListener3f(AL_POSITION, x, y, z);
+        alListener3f(AL_VELOCITY, vx, vy, vz);
+        alListenerfv(AL_ORIENTATION, &snapshot.orientation as *const Orientation as *const f32);
+        alListenerf(AL_GAIN, snapshot.gain);
+    }
+    check_al_error()
+}
+
+/// Reads the state of source `handle`, with base gain `gain`, looking up the hash of its buffer with `buffer_hash`.
+/// The context must be locked.
+pub(crate) fn capture_source(
+    handle: u32,
+    gain: f32,
+    buffer_hash: impl FnOnce(u32) -> Option<u64>,
+) -> AllenResult<SourceSnapshot> {
+    let get_f = |param| {
+        let mut value = 0.0;
+        unsafe { alGetSourcef(handle, param, &mut value) };
+        value
+    };
+    let get_3f = |param| {
+        let mut value = [0.0; 3];
+        unsafe { alGetSource3f(handle, param, &mut value[0], &mut value[1], &mut value[2]) };
+        value
+    };
+    let get_i = |param| {
+        let mut value = 0;
+        unsafe { alGetSourcei(handle, param, &mut value) };
+        value
+    };
+
+    let streaming = get_i(AL_SOURCE_TYPE) == AL_STREAMING;
+    let buffer = match get_i(AL_BUFFER) {
+        buffer if buffer != 0 && !streaming => buffer_hash(buffer as u32),
+        _ => None,
+    };
+
+    let snapshot = SourceSnapshot {
+        position: get_3f(AL_POSITION),
+        velocity: get_3f(AL_VELOCITY),
+        direction: get_3f(AL_DIRECTION),
+        gain,
+        pitch: get_f(AL_PITCH),
+        min_gain: get_f(AL_MIN_GAIN),
+        max_gain: get_f(AL_MAX_GAIN),
+        reference_distance: get_f(AL_REFERENCE_DISTANCE),
+        rolloff_factor: get_f(AL_ROLLOFF_FACTOR),
+        max_distance: get_f(AL_MAX_DISTANCE),
+        cone_inner_angle: get_f(AL_CONE_INNER_ANGLE),
+        cone_outer_angle: get_f(AL_CONE_OUTER_ANGLE),
+        cone_outer_gain: get_f(AL_CONE_OUTER_GAIN),
+        looping: get_i(AL_LOOPING) != 0,
+        relative: get_i(AL_SOURCE_RELATIVE) != 0,
+        buffer,
+        streaming,
+        offset_secs: get_f(AL_SEC_OFFSET),
+        state: SourceState::from_i32(get_i(AL_SOURCE_STATE)).unwrap_or(SourceState::Initial),
+    };
+    check_al_error()?;
+
+    Ok(snapshot)
+}
+
+/// Applies a source's state to source `handle`, attaching buffer `buffer` (0 for none) and setting `AL_GAIN` to
+/// `output_gain`. The queue and playback state of streaming sources are left alone. The context must be locked.
+pub(crate) fn apply_source(handle: u32, snapshot: &SourceSnapshot, buffer: u32, output_gain: f32) -> AllenResult<()> {
+    let set_f = |param, value| unsafe { alSourcef(handle, param, value) };
+    let set_3f = |param, [x, y, z]: Float3| unsafe { alSource3f(handle, param, x, y, z) };
+    let set_i = |param, value| unsafe { alSourcei(handle, param, value) };
+    let get_i = |param| {
+        let mut value = 0;
+        unsafe { alGetSourcei(handle, param, &mut value) };
+        value
+    };
+
+    // Setting AL_BUFFER would drop the queue.
+    let streaming = snapshot.streaming || get_i(AL_SOURCE_TYPE) == AL_STREAMING;
+    if !streaming {
+        // Buffers can only be swapped on a stopped source.
+        unsafe { alSourceStop(handle) };
+        set_i(AL_BUFFER, buffer as i32);
+    }
+
+    set_3f(AL_POSITION, snapshot.position);
+    set_3f(AL_VELOCITY, snapshot.velocity);
+    set_3f(AL_DIRECTION, snapshot.direction);
+    set_f(AL_GAIN, output_gain);
+    set_f(AL_PITCH, snapshot.pitch);
+    set_f(AL_MIN_GAIN, snapshot.min_gain);
+    set_f(AL_MAX_GAIN, snapshot.max_gain);
+    set_f(AL_REFERENCE_DISTANCE, snapshot.reference_distance);
+    set_f(AL_ROLLOFF_FACTOR, snapshot.rolloff_factor);
+    set_f(AL_MAX_DISTANCE, snapshot.max_distance);
+    set_f(AL_CONE_INNER_ANGLE, snapshot.cone_inner_angle);
+    set_f(AL_CONE_OUTER_ANGLE, snapshot.cone_outer_angle);
+    set_f(AL_CONE_OUTER_GAIN, snapshot.cone_outer_gain);
+    set_i(AL_LOOPING, snapshot.looping as i32);
+    set_i(AL_SOURCE_RELATIVE, snapshot.relative as i32);
+
+    if streaming {
+        return check_al_error();
+    }
+
+    match snapshot.state {
+        SourceState::Initial => unsafe { alSourceRewind(handle) },
+        state => {
+            // The offset of a stopped source applies on the next play.
+            set_f(AL_SEC_OFFSET, snapshot.offset_secs);
+            match state {
+                SourceState::Playing => unsafe { alSourcePlay(handle) },
+                SourceState::Paused => unsafe {
+                    alSourcePlay(handle);
+                    alSourcePause(handle);
+                },
+                _ => {}
+            }
+        }
+    }
+    check_al_error()
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::{test_buffer, test_context, SceneSnapshot};
+
+    #[test]
+    fn json_round_trip() {
+        let Some(context) = test_context() else { return };
+        context.set_track_buffer_content(true);
+        let buffer = test_buffer(&context, 44100);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.set_position([1.0, 2.0, 3.0]).unwrap();
+        source.set_gain(0.5).unwrap();
+        source.set_looping(true).unwrap();
+        context.listener().set_position([4.0, 0.0, 0.0]).unwrap();
+
+        let json = serde_json::to_string(&context.scene_snapshot().unwrap()).unwrap();
+        let snapshot: SceneSnapshot = serde_json::from_str(&json).unwrap();
+
+        source.set_buffer(None).unwrap();
+        source.set_position([0.0, 0.0, 0.0]).unwrap();
+        source.set_gain(1.0).unwrap();
+        source.set_looping(false).unwrap();
+        context.listener().set_position([0.0, 0.0, 0.0]).unwrap();
+
+        context.restore_scene(&snapshot).unwrap();
+        assert_eq!(source.position().unwrap(), [1.0, 2.0, 3.0]);
+        assert_eq!(source.gain().unwrap(), 0.5);
+        assert!(source.is_looping().unwrap());
+        assert_eq!(context.listener().position().unwrap(), [4.0, 0.0, 0.0]);
+        assert_eq!(context.scene_snapshot().unwrap().sources[0].buffer, snapshot.sources[0].buffer);
+        assert!(snapshot.sources[0].buffer.is_some());
+    }
+}
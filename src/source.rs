@@ -1,10 +1,23 @@
-use crate::{check_al_error, AllenResult, Buffer, Context, Float3, PropertiesContainer};
+use crate::{
+    check_al_error, AllenError, AllenResult, Buffer, BufferDescriptor, Context, EffectSlot, Filter, Float3,
+    GainCurve, GainRamp, PropertiesContainer,
+};
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
 use oal_sys_windows::*;
 
 /// The state of a [`Source`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SourceState {
     /// The initial state of a source. The source will also be placed in this state after calling [`Source::rewind`].
     Initial = AL_INITIAL as isize,
@@ -16,11 +29,194 @@ pub enum SourceState {
     Stopped = AL_STOPPED as isize,
 }
 
+/// An OpenAL distance attenuation model, as used by [`Source::effective_gain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceModel {
+    None,
+    Inverse,
+    InverseClamped,
+    Linear,
+    LinearClamped,
+    Exponent,
+    ExponentClamped,
+}
+
+impl DistanceModel {
+    /// The attenuation at `distance`, following the formulas of the OpenAL specification.
+    pub fn gain(&self, distance: f32, reference_distance: f32, max_distance: f32, rolloff_factor: f32) -> f32 {
+        let distance = match self {
+            DistanceModel::InverseClamped | DistanceModel::LinearClamped | DistanceModel::ExponentClamped => {
+                distance.max(reference_distance).min(max_distance)
+            }
+            _ => distance,
+        };
+
+        let gain = match self {
+            DistanceModel::None => 1.0,
+            DistanceModel::Inverse | DistanceModel::InverseClamped => {
+                reference_distance / (reference_distance + rolloff_factor * (distance - reference_distance))
+            }
+            DistanceModel::Linear | DistanceModel::LinearClamped => {
+                1.0 - rolloff_factor * (distance - reference_distance) / (max_distance - reference_distance)
+            }
+            DistanceModel::Exponent | DistanceModel::ExponentClamped => {
+                (distance / reference_distance).powf(-rolloff_factor)
+            }
+        };
+
+        // Degenerate parameters (e.g. a zero reference distance) produce NaN or infinity.
+        if gain.is_finite() {
+            gain.max(0.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Smallest pitch set by the pitch helpers; `AL_PITCH` must be greater than zero.
+const MIN_PITCH: f32 = f32::EPSILON;
+/// Largest pitch set by the pitch helpers, matching OpenAL Soft's internal limit.
+const MAX_PITCH: f32 = 255.0;
+
+/// A stop scheduled with [`Source::stop_at`]. Poll it from the update loop.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduledStop {
+    target_ns: i64,
+    done: bool,
+}
+
+impl ScheduledStop {
+    /// Stops `source` once the device clock has passed the target. Returns whether the source was stopped.
+    pub fn poll(&mut self, source: &Source) -> AllenResult<bool> {
+        if !self.done && source.context.device().clock_ns()? >= self.target_ns {
+            source.stop()?;
+            self.done = true;
+        }
+
+        Ok(self.done)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// Filters owned by a source, shared by [`Source::apply_occlusion`] and [`Source::set_reverb_mix`] so their
+/// effects combine instead of overwriting each other.
+struct SourceFilters {
+    direct: Filter,
+    /// One filter per auxiliary send.
+    sends: HashMap<i32, Filter>,
+    occlusion: f32,
+    dry: f32,
+    /// Wet gain per auxiliary send.
+    wet: HashMap<i32, f32>,
+}
+
+/// Crate-side factors multiplied into `AL_GAIN` by [`Source::recompute_gain`]. Occlusion is not one of them: it
+/// attenuates the direct path and the sends by different amounts (see [`Source::apply_occlusion`]), which a
+/// single `AL_GAIN` covering both can't express, so it's applied through the source's filters instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GainFactors {
+    /// Set by [`Source::set_gain`].
+    pub(crate) base: f32,
+    /// Set by [`Source::set_group_gain`].
+    group: f32,
+    /// The gain of the source's category, see [`Context::set_category_gain`].
+    pub(crate) category: f32,
+    /// Driven by [`Source::set_gain_curve`].
+    automation: f32,
+    /// Driven by the anti-click fades.
+    fade: f32,
+    /// Driven by the loop duck, see [`Source::set_loop_duck`].
+    duck: f32,
+}
+
+impl GainFactors {
+    pub(crate) fn product(&self) -> f32 {
+        self.base * self.group * self.category * self.automation * self.fade * self.duck
+    }
+}
+
+/// Distance-based resampler switching, see [`Source::set_auto_resampler_quality`].
+#[derive(Debug, Clone, Copy)]
+struct AutoResampler {
+    near: i32,
+    far: i32,
+    near_dist: f32,
+    far_dist: f32,
+}
+
+/// Distance-based reverb send level, see [`Source::set_distance_reverb`].
+#[derive(Debug, Clone, Copy)]
+struct DistanceReverb {
+    send: i32,
+    near_wet: f32,
+    far_wet: f32,
+    near_dist: f32,
+    far_dist: f32,
+}
+
+/// A playback position callback, see [`Source::add_marker`].
+struct Marker {
+    at: f32,
+    callback: Box<dyn FnMut() + Send>,
+    /// Whether the marker has fired during the current playthrough.
+    fired: bool,
+}
+
+/// Changes of the distance reverb's wet level smaller than this are not applied, to spare filter updates.
+const DISTANCE_REVERB_EPSILON: f32 = 1e-3;
+
+/// Once the smoothed pitch is this close to its target, it snaps to it.
+const PITCH_SMOOTHING_EPSILON: f32 = 1e-4;
+
+/// Fraction of the limit above which [`Source::set_pitch_limited`] switches to the best resampler.
+const PITCH_LIMIT_HIGH_QUALITY: f32 = 0.9;
+
+/// Largest gain [`Source::set_gain_normalized`] applies, +24 dB, so near-silent clips aren't blown up.
+const MAX_NORMALIZED_GAIN: f32 = 16.0;
+
 /// A source used to play [`Buffer`]s.
 /// NOTE: Sources are bound to a context.
 pub struct Source {
     handle: u32,
     context: Context,
+    /// A/B loop region in samples, driven by [`Source::pump`].
+    ab_loop: Cell<Option<(i32, i32)>>,
+    /// Length of the fades applied by [`Source::play`] and [`Source::stop`]; zero disables them.
+    anti_click: Cell<Duration>,
+    /// Shared with the context's category registry, so category gain changes can reach the source.
+    gain_factors: Rc<Cell<GainFactors>>,
+    category: RefCell<Option<String>>,
+    gain_ramp: Cell<Option<GainRamp>>,
+    /// Length of the dip applied when a loop helper wraps around; zero disables it.
+    loop_duck: Cell<Duration>,
+    duck_ramp: Cell<Option<GainRamp>>,
+    gain_curve: RefCell<Option<GainCurve>>,
+    /// Target pitch and smoothing time constant for [`Source::set_pitch_smoothed`].
+    pitch_smoothing: Cell<Option<(f32, f32)>>,
+    auto_resampler: Cell<Option<AutoResampler>>,
+    distance_reverb: Cell<Option<DistanceReverb>>,
+    priority: Cell<u8>,
+    start_at_loop: Cell<bool>,
+    /// Keeps the buffer set with [`Source::set_shared_buffer`] alive. Dropped after the source is deleted.
+    shared_buffer: RefCell<Option<Arc<Buffer>>>,
+    markers: RefCell<Vec<Marker>>,
+    /// Playback position at the last [`Source::pump`], in seconds, to detect loops.
+    marker_offset: Cell<f32>,
+    /// Whether buffers were ever queued, so [`Source::pump`] only tracks played buffers for streaming sources.
+    streaming: Cell<bool>,
+    /// AL_BUFFERS_PROCESSED when played buffers were last counted. It drops on unqueue, rewind and detach, so only
+    /// growth past it is added to `buffers_played`.
+    last_processed: Cell<u64>,
+    /// See [`Source::total_buffers_played`].
+    buffers_played: Cell<u64>,
+    /// Buffer kept queued twice by [`Source::pump`] for [`Source::loop_seamless`].
+    seamless_loop: Cell<Option<u32>>,
+    /// Effect slot handle per auxiliary send, as set by [`Source::set_aux_send`].
+    aux_sends: RefCell<HashMap<i32, u32>>,
+    filters: RefCell<Option<SourceFilters>>,
 }
 
 impl PropertiesContainer<f32> for Source {
@@ -152,7 +348,7 @@ impl PropertiesContainer<[i32; 3]> for Source {
 #[rustfmt::skip]
 impl Source {
     getter_setter!(pitch, set_pitch, f32, AL_PITCH);
-    getter_setter!(gain, set_gain, f32, AL_GAIN);
+    getter!(output_gain, f32, AL_GAIN);
     getter_setter!(min_gain, set_min_gain, f32, AL_MIN_GAIN);
     getter_setter!(max_gain, set_max_gain, f32, AL_MAX_GAIN);
     getter_setter!(max_distance, set_max_distance, f32, AL_MAX_DISTANCE);
@@ -181,6 +377,12 @@ impl Source {
     getter!(length_in_samples, i32, AL_SAMPLE_LENGTH_SOFT, "AL_SOFT_source_length");
     getter!(length_in_bytes, f32, AL_BYTE_LENGTH_SOFT, "AL_SOFT_source_length");
 
+    // AL_SOFT_source_resampler
+    getter_setter!(resampler, set_resampler, i32, AL_SOURCE_RESAMPLER_SOFT, "AL_SOFT_source_resampler");
+
+    // AL_SOFT_source_spatialize
+    getter_setter!(is_spatialized, set_spatialized, bool, AL_SOURCE_SPATIALIZE_SOFT, "AL_SOFT_source_spatialize");
+
     // AL_EXT_SOURCE_RADIUS
     getter_setter!(source_radius, set_source_radius, f32, AL_SOURCE_RADIUS, "AL_EXT_SOURCE_RADIUS");
 
@@ -191,6 +393,28 @@ impl Source {
                 Some(buffer) => buffer.handle() as i32,
                 None => 0,
             },
+        )?;
+        self.shared_buffer.borrow_mut().take();
+
+        Ok(())
+    }
+
+    /// Attaches a buffer from a [`crate::BufferManager`], keeping it alive for as long as it's attached.
+    pub fn set_shared_buffer(&self, buffer: Option<Arc<Buffer>>) -> AllenResult<()> {
+        self.set_buffer(buffer.as_deref())?;
+        *self.shared_buffer.borrow_mut() = buffer;
+
+        Ok(())
+    }
+
+    // ALC_EXT_EFX
+    pub fn set_direct_filter(&self, filter: Option<&Filter>) -> AllenResult<()> {
+        self.set(
+            AL_DIRECT_FILTER,
+            match filter {
+                Some(filter) => filter.handle() as i32,
+                None => AL_FILTER_NULL,
+            },
         )
     }
 
@@ -206,12 +430,792 @@ impl Source {
             alGenSources(1, &mut handle)
         };
 
+        // Drivers report running out of sources as either of these.
+        check_al_error().map_err(|err| match err {
+            AllenError::OutOfMemory | AllenError::InvalidValue => AllenError::SourceLimitReached,
+            err => err,
+        })?;
+
+        let gain_factors = Rc::new(Cell::new(GainFactors {
+            base: 1.0,
+            group: 1.0,
+            category: 1.0,
+            automation: 1.0,
+            fade: 1.0,
+            duck: 1.0,
+        }));
+        context.register_source(handle, Rc::downgrade(&gain_factors));
+
+        Ok(Self {
+            handle,
+            context,
+            ab_loop: Cell::new(None),
+            anti_click: Cell::new(Duration::ZERO),
+            gain_factors,
+            category: RefCell::new(None),
+            gain_ramp: Cell::new(None),
+            loop_duck: Cell::new(Duration::ZERO),
+            duck_ramp: Cell::new(None),
+            gain_curve: RefCell::new(None),
+            pitch_smoothing: Cell::new(None),
+            auto_resampler: Cell::new(None),
+            distance_reverb: Cell::new(None),
+            priority: Cell::new(0),
+            start_at_loop: Cell::new(false),
+            shared_buffer: RefCell::new(None),
+            markers: RefCell::new(Vec::new()),
+            marker_offset: Cell::new(0.0),
+            streaming: Cell::new(false),
+            last_processed: Cell::new(0),
+            buffers_played: Cell::new(0),
+            seamless_loop: Cell::new(None),
+            aux_sends: RefCell::new(HashMap::new()),
+            filters: RefCell::new(None),
+        })
+    }
+
+    pub(crate) fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// The sample rate of the currently attached buffer.
+    fn buffer_frequency(&self) -> AllenResult<i32> {
+        let buffer: i32 = self.get(AL_BUFFER)?;
+
+        let _lock = self.context.make_current();
+
+        let result = unsafe {
+            let mut value = 0;
+            alGetBufferi(buffer as u32, AL_FREQUENCY, &mut value);
+            value
+        };
+
         check_al_error()?;
 
-        Ok(Self { handle, context })
+        Ok(result)
+    }
+
+    /// Makes [`Source::play`] start from the attached buffer's loop start (see [`Buffer::set_loop_points`]) instead
+    /// of the beginning, e.g. to skip a music intro on retrigger. Resuming a paused source is unaffected.
+    /// Requires extension ``AL_SOFT_loop_points``.
+    pub fn set_start_at_loop(&self, start_at_loop: bool) -> AllenResult<()> {
+        crate::check_al_extension(&std::ffi::CString::new("AL_SOFT_loop_points").unwrap())?;
+        self.start_at_loop.set(start_at_loop);
+
+        Ok(())
+    }
+
+    pub fn is_start_at_loop(&self) -> bool {
+        self.start_at_loop.get()
+    }
+
+    /// Seeks to the attached buffer's loop start, see [`Source::set_start_at_loop`].
+    fn seek_to_loop_start(&self) -> AllenResult<()> {
+        let buffer: i32 = self.get(AL_BUFFER)?;
+        if buffer == 0 {
+            return Ok(());
+        }
+
+        let _lock = self.context.make_current();
+
+        let loop_points = unsafe {
+            let mut value = [0i32; 2];
+            alGetBufferiv(buffer as u32, AL_LOOP_POINTS_SOFT, value.as_mut_ptr());
+            value
+        };
+        check_al_error()?;
+
+        unsafe { alSourcei(self.handle, AL_SAMPLE_OFFSET, loop_points[0]) };
+        check_al_error()
+    }
+
+    /// Loops playback between `start` and `end` of the attached buffer, independently of the buffer's loop points.
+    /// The wrap happens in [`Source::pump`], which must be called regularly.
+    pub fn set_ab_loop(&self, start: Duration, end: Duration) -> AllenResult<()> {
+        if start >= end {
+            return Err(AllenError::InvalidValue);
+        }
+
+        let frequency = self.buffer_frequency()? as f64;
+        self.ab_loop.set(Some((
+            (start.as_secs_f64() * frequency) as i32,
+            (end.as_secs_f64() * frequency) as i32,
+        )));
+
+        Ok(())
+    }
+
+    pub fn clear_ab_loop(&self) {
+        self.ab_loop.set(None);
+    }
+
+    /// Drives crate-side playback features such as the A/B loop. Call this regularly, e.g. once per frame.
+    pub fn pump(&self) -> AllenResult<()> {
+        if let Some((start, end)) = self.ab_loop.get() {
+            if self.time_in_samples()? >= end {
+                self.set_time_in_samples(start)?;
+                self.duck()?;
+            }
+        }
+
+        if let Some(buffer) = self.seamless_loop.get() {
+            let processed = self.buffers_processed()?;
+            if processed > 0 {
+                self.unqueue_buffers(processed)?;
+                self.queue_handles(&vec![buffer; processed as usize])?;
+            }
+        }
+
+        if !self.markers.borrow().is_empty() {
+            self.fire_markers()?;
+        }
+
+        if self.streaming.get() {
+            self.count_played_buffers()?;
+        }
+
+        Ok(())
+    }
+
+    fn count_played_buffers(&self) -> AllenResult<()> {
+        let processed = self.buffers_processed()? as u64;
+        let last = self.last_processed.get();
+        if processed > last {
+            self.buffers_played
+                .set(self.buffers_played.get() + processed - last);
+        }
+        self.last_processed.set(processed);
+
+        Ok(())
+    }
+
+    /// How many queued buffers the source has played through since its creation, as of the last
+    /// [`Source::pump`]. A jump by more than one between pumps means buffers were played without being refilled.
+    pub fn total_buffers_played(&self) -> u64 {
+        self.buffers_played.get()
+    }
+
+    /// Calls `callback` once playback passes `at`, e.g. to sync visuals to a beat. Markers are checked by
+    /// [`Source::pump`], so they fire with its granularity, and fire again after the source loops or restarts.
+    pub fn add_marker(&self, at: Duration, callback: Box<dyn FnMut() + Send>) {
+        self.markers.borrow_mut().push(Marker {
+            at: at.as_secs_f32(),
+            callback,
+            fired: false,
+        });
+    }
+
+    pub fn clear_markers(&self) {
+        self.markers.borrow_mut().clear();
+    }
+
+    fn fire_markers(&self) -> AllenResult<()> {
+        let offset = self.time_in_secs()?;
+        let looped = offset < self.marker_offset.get();
+        self.marker_offset.set(offset);
+        let playing = self.state()? == SourceState::Playing;
+
+        // Taken out while firing, so callbacks may add markers.
+        let mut markers = self.markers.take();
+        for marker in &mut markers {
+            if looped {
+                marker.fired = false;
+            }
+            if playing && !marker.fired && marker.at <= offset {
+                marker.fired = true;
+                (marker.callback)();
+            }
+        }
+
+        let mut added = self.markers.borrow_mut();
+        markers.append(&mut added);
+        *added = markers;
+
+        Ok(())
+    }
+
+    /// Briefly dips the gain at the wrap of the A/B loop, hiding residual clicks at the boundary. 2-5 ms is usually
+    /// enough. The gain recovers over `duck` in [`Source::update`]. Passing [`Duration::ZERO`] disables this.
+    ///
+    /// Seamless loops aren't ducked: their wrap happens in the mixer and is only noticed by [`Source::pump`] up to a
+    /// frame later, which would dip the gain after the boundary rather than at it.
+    pub fn set_loop_duck(&self, duck: Duration) {
+        self.loop_duck.set(duck);
+    }
+
+    fn duck(&self) -> AllenResult<()> {
+        let duck = self.loop_duck.get();
+        if duck.is_zero() {
+            return Ok(());
+        }
+
+        self.duck_ramp
+            .set(Some(GainRamp::new(0.0, 1.0, duck, false)));
+        self.modify_gain(|factors| factors.duck = 0.0)
+    }
+
+    /// Advances crate-side time-based features such as gain ramps by `dt`, then calls [`Source::pump`].
+    /// Call this regularly, e.g. once per frame.
+    pub fn update(&self, dt: Duration) -> AllenResult<()> {
+        let mut factors = self.gain_factors.get();
+
+        if let Some(mut ramp) = self.gain_ramp.get() {
+            ramp.advance(dt);
+            factors.fade = ramp.value();
+
+            if ramp.is_done() {
+                self.gain_ramp.set(None);
+
+                if ramp.stop_when_done {
+                    self.stop_al_source()?;
+                    // Reset the fade so the next play isn't silent.
+                    factors.fade = 1.0;
+                }
+            } else {
+                self.gain_ramp.set(Some(ramp));
+            }
+        }
+
+        if let Some(mut ramp) = self.duck_ramp.get() {
+            ramp.advance(dt);
+            factors.duck = ramp.value();
+            self.duck_ramp.set((!ramp.is_done()).then_some(ramp));
+        }
+
+        let automation = self.gain_curve.borrow_mut().as_mut().and_then(|curve| {
+            curve.advance(dt);
+            curve.value()
+        });
+        if let Some(automation) = automation {
+            factors.automation = automation;
+        }
+
+        if factors != self.gain_factors.get() {
+            self.gain_factors.set(factors);
+            self.recompute_gain()?;
+        }
+
+        if let Some((target, smoothing)) = self.pitch_smoothing.get() {
+            let pitch = self.pitch()?;
+            let alpha = 1.0 - (-dt.as_secs_f32() / smoothing).exp();
+            let pitch = pitch + (target - pitch) * alpha;
+
+            if (target - pitch).abs() < PITCH_SMOOTHING_EPSILON {
+                self.pitch_smoothing.set(None);
+                self.set_pitch(target)?;
+            } else {
+                self.set_pitch(pitch)?;
+            }
+        }
+
+        if let Some(auto) = self.auto_resampler.get() {
+            let distance = self.listener_distance()?;
+
+            // Between the two distances the current resampler is kept, so sources don't flip back and forth.
+            let resampler = if distance <= auto.near_dist {
+                Some(auto.near)
+            } else if distance >= auto.far_dist {
+                Some(auto.far)
+            } else {
+                None
+            };
+
+            if let Some(resampler) = resampler {
+                if self.resampler()? != resampler {
+                    self.set_resampler(resampler)?;
+                }
+            }
+        }
+
+        if let Some(reverb) = self.distance_reverb.get() {
+            self.apply_distance_reverb(reverb)?;
+        }
+
+        self.pump()
+    }
+
+    fn listener_distance(&self) -> AllenResult<f32> {
+        let [x, y, z] = self.context.listener_to_source_vector(self)?;
+        Ok((x * x + y * y + z * z).sqrt())
+    }
+
+    /// Routes auxiliary send `send` into `slot` with a wet level following the distance to the listener in
+    /// [`Source::update`], so distant sources sound more reverberant: `near_wet` within `near_dist`, `far_wet`
+    /// beyond `far_dist`, and linearly in between. Requires extension ``ALC_EXT_EFX``.
+    pub fn set_distance_reverb(
+        &self,
+        slot: &EffectSlot,
+        send: i32,
+        near_wet: f32,
+        far_wet: f32,
+        near_dist: f32,
+        far_dist: f32,
+    ) -> AllenResult<()> {
+        if near_dist > far_dist {
+            return Err(AllenError::InvalidValue);
+        }
+
+        let reverb = DistanceReverb {
+            send,
+            near_wet,
+            far_wet,
+            near_dist,
+            far_dist,
+        };
+
+        self.aux_sends.borrow_mut().insert(send, slot.handle());
+        self.distance_reverb.set(Some(reverb));
+        // Forget the previous level so the new routing is applied right away.
+        if let Some(filters) = self.filters.borrow_mut().as_mut() {
+            filters.wet.remove(&send);
+        }
+        self.apply_distance_reverb(reverb)
+    }
+
+    /// Stops scaling the send set with [`Source::set_distance_reverb`], leaving its current wet level.
+    pub fn clear_distance_reverb(&self) {
+        self.distance_reverb.set(None);
+    }
+
+    fn apply_distance_reverb(&self, reverb: DistanceReverb) -> AllenResult<()> {
+        let distance = self.listener_distance()?;
+
+        let t = if reverb.far_dist > reverb.near_dist {
+            ((distance - reverb.near_dist) / (reverb.far_dist - reverb.near_dist)).clamp(0.0, 1.0)
+        } else if distance < reverb.far_dist {
+            0.0
+        } else {
+            1.0
+        };
+        let wet = reverb.near_wet + (reverb.far_wet - reverb.near_wet) * t;
+
+        let current = self
+            .filters
+            .borrow()
+            .as_ref()
+            .and_then(|filters| filters.wet.get(&reverb.send).copied());
+        if current.is_some_and(|current| (current - wet).abs() < DISTANCE_REVERB_EPSILON) {
+            return Ok(());
+        }
+
+        self.update_filters(|filters| {
+            filters.wet.insert(reverb.send, wet);
+        })
+    }
+
+    /// Sets the position, velocity and direction from the previous and current column-major 4x4 transforms, `dt`
+    /// seconds apart. The direction is the transform's forward axis, -Z as in OpenGL. `dt` must be positive.
+    pub fn set_from_transforms(&self, prev: &[f32; 16], cur: &[f32; 16], dt: f32) -> AllenResult<()> {
+        if dt <= 0.0 {
+            return Err(AllenError::InvalidValue);
+        }
+
+        let position = [cur[12], cur[13], cur[14]];
+        let velocity = [
+            (cur[12] - prev[12]) / dt,
+            (cur[13] - prev[13]) / dt,
+            (cur[14] - prev[14]) / dt,
+        ];
+
+        let forward = [-cur[8], -cur[9], -cur[10]];
+        let length = (forward[0] * forward[0] + forward[1] * forward[1] + forward[2] * forward[2]).sqrt();
+        let direction = if length > 0.0 {
+            [forward[0] / length, forward[1] / length, forward[2] / length]
+        } else {
+            forward
+        };
+
+        let _lock = self.context.make_current();
+        unsafe {
+            alSource3f(self.handle, AL_POSITION, position[0], position[1], position[2]);
+            alSource3f(self.handle, AL_VELOCITY, velocity[0], velocity[1], velocity[2]);
+            alSource3f(self.handle, AL_DIRECTION, direction[0], direction[1], direction[2]);
+        }
+        check_al_error()
+    }
+
+    /// An estimate of the gain the source is heard at from `listener_pos`, combining its gain, distance attenuation
+    /// under `model` and its cone, clamped to its min/max gain. Listener gain and filters are not included.
+    pub fn effective_gain(&self, listener_pos: [f32; 3], model: DistanceModel) -> AllenResult<f32> {
+        let position = self.position()?;
+        // From the source to the listener.
+        let to_listener = if self.is_relative()? {
+            [-position[0], -position[1], -position[2]]
+        } else {
+            [
+                listener_pos[0] - position[0],
+                listener_pos[1] - position[1],
+                listener_pos[2] - position[2],
+            ]
+        };
+
+        let length = |v: [f32; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        let distance = length(to_listener);
+
+        let distance_gain = model.gain(
+            distance,
+            self.reference_distance()?,
+            self.max_distance()?,
+            self.rolloff_factor()?,
+        );
+
+        let direction = self.direction()?;
+        let cone_gain = if length(direction) == 0.0 || distance == 0.0 {
+            // Omnidirectional, or the listener is right on the source.
+            1.0
+        } else {
+            let cos = (direction[0] * to_listener[0] + direction[1] * to_listener[1] + direction[2] * to_listener[2])
+                / (length(direction) * distance);
+            let angle = cos.clamp(-1.0, 1.0).acos().to_degrees();
+
+            // Cone angles cover both sides of the direction.
+            let inner = self.cone_inner_angle()? / 2.0;
+            let outer = self.cone_outer_angle()? / 2.0;
+            let outer_gain = self.cone_outer_gain()?;
+
+            if angle <= inner {
+                1.0
+            } else if angle >= outer {
+                outer_gain
+            } else {
+                1.0 + (outer_gain - 1.0) * (angle - inner) / (outer - inner)
+            }
+        };
+
+        let gain = self.output_gain()? * distance_gain * cone_gain;
+
+        Ok(gain.max(self.min_gain()?).min(self.max_gain()?))
+    }
+
+    /// Whether the [`Source::effective_gain`] from `listener_pos` reaches `threshold`, so far away sources can be
+    /// culled.
+    pub fn is_audible(&self, listener_pos: [f32; 3], threshold: f32, model: DistanceModel) -> AllenResult<bool> {
+        Ok(self.effective_gain(listener_pos, model)? >= threshold)
+    }
+
+    /// Switches between resamplers by distance to the listener in [`Source::update`], to save CPU on far away
+    /// sources: the `near` resampler index is used within `near_dist`, the `far` one beyond `far_dist`.
+    /// Requires extension ``AL_SOFT_source_resampler``.
+    pub fn set_auto_resampler_quality(
+        &self,
+        near: i32,
+        far: i32,
+        near_dist: f32,
+        far_dist: f32,
+    ) -> AllenResult<()> {
+        crate::check_al_extension(&std::ffi::CString::new("AL_SOFT_source_resampler").unwrap())?;
+        if near_dist > far_dist {
+            return Err(AllenError::InvalidValue);
+        }
+
+        self.auto_resampler.set(Some(AutoResampler {
+            near,
+            far,
+            near_dist,
+            far_dist,
+        }));
+
+        Ok(())
+    }
+
+    pub fn clear_auto_resampler_quality(&self) {
+        self.auto_resampler.set(None);
+    }
+
+    /// The priority used by [`crate::SourcePool`] when stealing voices; lower priorities are stolen first.
+    pub fn priority(&self) -> u8 {
+        self.priority.get()
+    }
+
+    pub fn set_priority(&self, priority: u8) {
+        self.priority.set(priority);
+    }
+
+    /// The base gain, as last set with [`Source::set_gain`], before the group gain, automation, fades and ducking
+    /// are applied. This is not `AL_GAIN`, which holds the product of all of them: read the gain actually applied
+    /// with [`Source::output_gain`].
+    pub fn gain(&self) -> AllenResult<f32> {
+        Ok(self.gain_factors.get().base)
+    }
+
+    /// Sets the base gain. The gain actually applied (see [`Source::output_gain`]) is the product of the base gain,
+    /// the group gain, the gain curve, anti-click fades and the loop duck.
+    pub fn set_gain(&self, value: f32) -> AllenResult<()> {
+        self.modify_gain(|factors| factors.base = value)
+    }
+
+    /// Sets the base gain so that `descriptor`'s audio plays at `target_lufs`, judged by
+    /// [`BufferDescriptor::integrated_loudness`]. The gain is capped at +24 dB; silent audio gets a gain of 1.0.
+    /// Gains above 1.0 are only heard if [`Source::max_gain`] allows them.
+    pub fn set_gain_normalized(&self, descriptor: &BufferDescriptor, target_lufs: f32) -> AllenResult<()> {
+        let loudness = descriptor.integrated_loudness();
+        let gain = if loudness.is_finite() {
+            10f32
+                .powf((target_lufs - loudness) / 20.0)
+                .clamp(0.0, MAX_NORMALIZED_GAIN)
+        } else {
+            1.0
+        };
+
+        self.set_gain(gain)
+    }
+
+    pub fn group_gain(&self) -> f32 {
+        self.gain_factors.get().group
+    }
+
+    /// Sets the gain of the group this source belongs to, which is multiplied with its own gain.
+    pub fn set_group_gain(&self, value: f32) -> AllenResult<()> {
+        self.modify_gain(|factors| factors.group = value)
+    }
+
+    pub fn category(&self) -> Option<String> {
+        self.category.borrow().clone()
+    }
+
+    /// Assigns the source to a mixer category (e.g. "music", "sfx" or "voice"), whose gain set with
+    /// [`Context::set_category_gain`] is multiplied with the source's own. `None` removes it from its category.
+    pub fn set_category(&self, category: Option<&str>) -> AllenResult<()> {
+        let mut current = self.category.borrow_mut();
+        if let Some(previous) = current.take() {
+            self.context.unregister_category_source(&previous, self.handle);
+        }
+
+        let gain = match category {
+            Some(category) => {
+                *current = Some(category.to_string());
+                self.context.register_category_source(
+                    category,
+                    self.handle,
+                    Rc::downgrade(&self.gain_factors),
+                )
+            }
+            None => 1.0,
+        };
+
+        self.modify_gain(|factors| factors.category = gain)
+    }
+
+    fn modify_gain(&self, modify: impl FnOnce(&mut GainFactors)) -> AllenResult<()> {
+        let mut factors = self.gain_factors.get();
+        modify(&mut factors);
+        self.gain_factors.set(factors);
+
+        self.recompute_gain()
+    }
+
+    /// Writes the product of every gain factor to `AL_GAIN`: base × group × category × automation × fade × duck.
+    /// The gain modifiers call this themselves. Occlusion is applied through filters, see
+    /// [`Source::apply_occlusion`].
+    pub fn recompute_gain(&self) -> AllenResult<()> {
+        self.set(AL_GAIN, self.gain_factors.get().product())
+    }
+
+    /// Automates the gain along piecewise-linear `keyframes` of (time, gain), starting now. The curve's gain is
+    /// relative to the base gain and is applied by [`Source::update`]. A looping curve restarts after its last
+    /// keyframe; otherwise the last gain is held.
+    pub fn set_gain_curve(&self, keyframes: &[(Duration, f32)], looping: bool) {
+        *self.gain_curve.borrow_mut() = Some(GainCurve::new(keyframes, looping));
+    }
+
+    pub fn clear_gain_curve(&self) -> AllenResult<()> {
+        *self.gain_curve.borrow_mut() = None;
+        self.modify_gain(|factors| factors.automation = 1.0)
+    }
+
+    /// Fades the gain in on [`Source::play`] and out on [`Source::stop`] over `fade` to avoid clicks.
+    /// The fades are driven by [`Source::update`]. Passing [`Duration::ZERO`] disables them.
+    pub fn set_anti_click(&self, fade: Duration) {
+        self.anti_click.set(fade);
+    }
+
+    /// Routes auxiliary send `send` into `slot`, through an optional `filter`. Passing `None` as the slot disconnects the send.
+    pub fn set_aux_send(
+        &self,
+        send: i32,
+        slot: Option<&EffectSlot>,
+        filter: Option<&Filter>,
+    ) -> AllenResult<()> {
+        let slot = slot.map(|slot| slot.handle()).unwrap_or(AL_EFFECTSLOT_NULL as u32);
+        let filter = filter.map(|filter| filter.handle()).unwrap_or(AL_FILTER_NULL as u32);
+
+        self.set(AL_AUXILIARY_SEND_FILTER, [slot as i32, send, filter as i32])?;
+
+        let mut aux_sends = self.aux_sends.borrow_mut();
+        if slot == AL_EFFECTSLOT_NULL as u32 {
+            aux_sends.remove(&send);
+        } else {
+            aux_sends.insert(send, slot);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn aux_send(&self, send: i32) -> Option<u32> {
+        self.aux_sends.borrow().get(&send).copied()
+    }
+
+    /// Simulates the source being behind an obstacle, from `0.0` (clear) to `1.0` (fully occluded).
+    /// This low-passes the direct path and, to a lesser degree, every auxiliary send set with
+    /// [`Source::set_aux_send`], replacing any filters set on them. Requires extension ``ALC_EXT_EFX``.
+    pub fn apply_occlusion(&self, amount: f32) -> AllenResult<()> {
+        self.update_filters(|filters| filters.occlusion = amount.clamp(0.0, 1.0))
+    }
+
+    /// Routes auxiliary send `send` into `slot` and balances it against the direct path, from `0.0` (dry) to
+    /// `1.0` (wet). The balance is an equal-power crossfade, so the perceived loudness stays roughly constant.
+    /// Requires extension ``ALC_EXT_EFX``.
+    pub fn set_reverb_mix(&self, slot: &EffectSlot, send: i32, wet: f32) -> AllenResult<()> {
+        let angle = wet.clamp(0.0, 1.0) * std::f32::consts::FRAC_PI_2;
+
+        self.aux_sends.borrow_mut().insert(send, slot.handle());
+        self.update_filters(|filters| {
+            filters.dry = angle.cos();
+            filters.wet.insert(send, angle.sin());
+        })
+    }
+
+    /// Modifies the source's own filters, then writes them to the direct path and every auxiliary send.
+    fn update_filters(&self, modify: impl FnOnce(&mut SourceFilters)) -> AllenResult<()> {
+        let efx = self.context.efx()?;
+
+        let mut filters = self.filters.borrow_mut();
+        if filters.is_none() {
+            *filters = Some(SourceFilters {
+                direct: Filter::new(self.context.clone())?,
+                sends: HashMap::new(),
+                occlusion: 0.0,
+                dry: 1.0,
+                wet: HashMap::new(),
+            });
+        }
+        let filters = filters.as_mut().unwrap();
+        modify(filters);
+
+        let aux_sends = self.aux_sends.borrow();
+        for index in aux_sends.keys() {
+            if !filters.sends.contains_key(index) {
+                filters.sends.insert(*index, Filter::new(self.context.clone())?);
+            }
+        }
+
+        let occlusion = filters.occlusion;
+        let lerp = |clear: f32, occluded: f32| clear + (occluded - clear) * occlusion;
+
+        let _lock = self.context.make_current();
+
+        unsafe {
+            let filteri = efx.filteri.unwrap();
+            let filterf = efx.filterf.unwrap();
+
+            let direct = filters.direct.handle();
+            filteri(direct, AL_FILTER_TYPE, AL_FILTER_LOWPASS);
+            filterf(direct, AL_LOWPASS_GAIN, lerp(1.0, 0.3) * filters.dry);
+            filterf(direct, AL_LOWPASS_GAINHF, lerp(1.0, 0.05));
+            alSourcei(self.handle, AL_DIRECT_FILTER, direct as i32);
+
+            for (index, slot) in aux_sends.iter() {
+                let send = filters.sends[index].handle();
+                let wet = filters.wet.get(index).copied().unwrap_or(1.0);
+
+                // Reflections reach around the obstacle, so sends are dampened less than the direct path.
+                filteri(send, AL_FILTER_TYPE, AL_FILTER_LOWPASS);
+                filterf(send, AL_LOWPASS_GAIN, lerp(1.0, 0.5) * wet);
+                filterf(send, AL_LOWPASS_GAINHF, lerp(1.0, 0.25));
+                alSource3i(self.handle, AL_AUXILIARY_SEND_FILTER, *slot as i32, *index, send as i32);
+            }
+        }
+
+        check_al_error()
+    }
+
+    /// Compensates the pitch for `buffer` having been uploaded with a wrong sample rate, so it plays back at its
+    /// true speed. `native_rate` is the rate the audio was actually recorded at.
+    pub fn set_playback_rate(&self, native_rate: i32, buffer: &Buffer) -> AllenResult<()> {
+        let declared_rate = buffer.frequency()?;
+        if native_rate <= 0 || declared_rate <= 0 {
+            return Err(AllenError::InvalidValue);
+        }
+
+        let pitch = (native_rate as f32 / declared_rate as f32).clamp(MIN_PITCH, MAX_PITCH);
+        self.set_pitch(pitch)
+    }
+
+    /// Glides the pitch toward `target` instead of jumping, avoiding zipper noise when it changes rapidly (e.g. engine
+    /// RPM). `smoothing` is the time constant in seconds: after that long, about 63% of the distance is covered.
+    /// The pitch is written by [`Source::update`].
+    pub fn set_pitch_smoothed(&self, target: f32, smoothing: f32) -> AllenResult<()> {
+        let target = target.clamp(MIN_PITCH, MAX_PITCH);
+
+        if smoothing <= 0.0 {
+            self.pitch_smoothing.set(None);
+            return self.set_pitch(target);
+        }
+
+        self.pitch_smoothing.set(Some((target, smoothing)));
+        Ok(())
+    }
+
+    /// Sets the pitch as an offset in semitones, where 12 semitones double the pitch.
+    /// The resulting pitch is clamped to OpenAL's valid range, which must stay positive.
+    pub fn set_pitch_semitones(&self, semitones: f32) -> AllenResult<()> {
+        let pitch = 2f32.powf(semitones / 12.0).clamp(MIN_PITCH, MAX_PITCH);
+        self.set_pitch(pitch)
+    }
+
+    /// Sets the pitch, clamped to `max`. Near the limit, where a low quality resampler audibly aliases, the source
+    /// is switched to the best resampler if extension ``AL_SOFT_source_resampler`` is available.
+    pub fn set_pitch_limited(&self, pitch: f32, max: f32) -> AllenResult<()> {
+        let max = max.clamp(MIN_PITCH, MAX_PITCH);
+        let pitch = pitch.clamp(MIN_PITCH, max);
+        self.set_pitch(pitch)?;
+
+        if pitch >= max * PITCH_LIMIT_HIGH_QUALITY
+            && crate::is_extension_present(&std::ffi::CString::new("AL_SOFT_source_resampler").unwrap())?
+        {
+            // Resamplers are listed from lowest to highest quality.
+            let best = {
+                let _lock = self.context.make_current();
+                let value = unsafe { alGetInteger(AL_NUM_RESAMPLERS_SOFT) } - 1;
+                check_al_error()?;
+                value
+            };
+
+            if self.resampler()? < best {
+                self.set_resampler(best)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The pitch as an offset in semitones. See [`Source::set_pitch_semitones`].
+    pub fn pitch_semitones(&self) -> AllenResult<f32> {
+        Ok(12.0 * self.pitch()?.log2())
     }
 
     pub fn play(&self) -> AllenResult<()> {
+        if self.start_at_loop.get() {
+            match self.state()? {
+                SourceState::Paused => {}
+                state => {
+                    // Playing a playing source restarts it from the beginning, regardless of the offset.
+                    if state == SourceState::Playing {
+                        self.rewind()?;
+                    }
+                    self.seek_to_loop_start()?;
+                }
+            }
+        }
+
+        let fade = self.anti_click.get();
+        if !fade.is_zero() {
+            self.gain_ramp.set(Some(GainRamp::new(0.0, 1.0, fade, false)));
+            self.modify_gain(|factors| factors.fade = 0.0)?;
+        }
+
+        let _lock = self.context.make_current();
         unsafe { 
             alDistanceModel(AL_LINEAR_DISTANCE_CLAMPED);
             alSourcePlay(self.handle)
@@ -221,26 +1225,136 @@ impl Source {
     }
 
     pub fn pause(&self) -> AllenResult<()> {
+        let _lock = self.context.make_current();
         unsafe { alSourcePause(self.handle) };
         check_al_error()
     }
 
+    /// Schedules a stop for when the device clock (see [`crate::Device::clock_ns`]) reaches `device_clock_ns`.
+    /// The stop happens when the returned handle is polled past that point.
+    /// Requires extension ``ALC_SOFT_device_clock``.
+    pub fn stop_at(&self, device_clock_ns: i64) -> AllenResult<ScheduledStop> {
+        // Surface a missing extension now rather than on the first poll.
+        self.context.device().clock_ns()?;
+
+        Ok(ScheduledStop {
+            target_ns: device_clock_ns,
+            done: false,
+        })
+    }
+
+    /// Stops the source. With anti-click enabled, the source fades out first and is stopped by [`Source::update`].
     pub fn stop(&self) -> AllenResult<()> {
-        unsafe { alSourceStop(self.handle) };
-        check_al_error()
+        let fade = self.anti_click.get();
+        if !fade.is_zero() && self.state()? == SourceState::Playing {
+            // Fade out from wherever an interrupted fade-in got to, at the same rate.
+            let from = self.gain_factors.get().fade;
+            self.gain_ramp
+                .set(Some(GainRamp::new(from, 0.0, fade.mul_f32(from), true)));
+            return Ok(());
+        }
+
+        self.stop_now()
+    }
+
+    /// Removes the attached buffer. A playing or paused source is stopped first, since detaching a buffer mid-play
+    /// behaves differently across drivers; the source ends up stopped either way.
+    pub fn detach_buffer(&self) -> AllenResult<()> {
+        if matches!(self.state()?, SourceState::Playing | SourceState::Paused) {
+            self.stop_now()?;
+        }
+
+        self.detach_buffer_unchecked()
+    }
+
+    /// Removes the attached buffer without stopping the source first, leaving the outcome to the driver.
+    pub fn detach_buffer_unchecked(&self) -> AllenResult<()> {
+        self.set_buffer(None)
+    }
+
+    /// Stops the source right away, cancelling any fade.
+    pub(crate) fn stop_now(&self) -> AllenResult<()> {
+        if self.gain_ramp.take().is_some() {
+            self.modify_gain(|factors| factors.fade = 1.0)?;
+        }
+
+        self.stop_al_source()
+    }
+
+    /// Stops the AL source. Stopping marks every queued buffer as processed, played or not, so for
+    /// [`Source::total_buffers_played`] only the buffers processed before the stop are counted.
+    fn stop_al_source(&self) -> AllenResult<()> {
+        if self.streaming.get() {
+            self.count_played_buffers()?;
+        }
+
+        {
+            let _lock = self.context.make_current();
+            unsafe { alSourceStop(self.handle) };
+            check_al_error()?;
+        }
+
+        if self.streaming.get() {
+            self.last_processed.set(self.buffers_processed()? as u64);
+        }
+
+        Ok(())
+    }
+
+    /// Ramps the fade gain from its current value to `to` over `duration`, driven by [`Source::update`].
+    pub(crate) fn fade_to(&self, to: f32, duration: Duration, stop_when_done: bool) {
+        let from = self.gain_factors.get().fade;
+        self.gain_ramp
+            .set(Some(GainRamp::new(from, to, duration, stop_when_done)));
+    }
+
+    /// Silences the source, then ramps the fade gain back up to 1.0 over `duration`.
+    pub(crate) fn fade_in(&self, duration: Duration) -> AllenResult<()> {
+        self.modify_gain(|factors| factors.fade = 0.0)?;
+        self.fade_to(1.0, duration, false);
+
+        Ok(())
+    }
+
+    pub(crate) fn is_fading(&self) -> bool {
+        self.gain_ramp.get().is_some()
     }
 
     pub fn rewind(&self) -> AllenResult<()> {
+        let _lock = self.context.make_current();
         unsafe { alSourceRewind(self.handle) };
         check_al_error()
     }
 
+    /// Loops `buffer` by keeping two copies of it queued, refilled by [`Source::pump`], instead of relying on
+    /// `AL_LOOPING`, which can glitch at the wrap on some drivers. The source must not be playing.
+    pub fn loop_seamless(&self, buffer: &Buffer) -> AllenResult<()> {
+        self.set_looping(false)?;
+        self.set_buffer(None)?;
+        self.queue_buffers(&[buffer, buffer])?;
+        self.seamless_loop.set(Some(buffer.handle()));
+
+        Ok(())
+    }
+
+    /// Stops refilling the queue for [`Source::loop_seamless`]; playback ends once the queued copies finish.
+    pub fn stop_seamless_loop(&self) {
+        self.seamless_loop.set(None);
+    }
+
     pub fn queue_buffers(&self, buffers: &[&Buffer]) -> AllenResult<()> {
         let buffers = buffers
             .iter()
             .map(|buffer| buffer.handle())
             .collect::<Vec<_>>();
 
+        self.queue_handles(&buffers)
+    }
+
+    fn queue_handles(&self, buffers: &[u32]) -> AllenResult<()> {
+        self.streaming.set(true);
+
+        let _lock = self.context.make_current();
         unsafe {
             alSourceQueueBuffers(
                 self.handle,
@@ -257,19 +1371,514 @@ impl Source {
     }
 
     pub fn unqueue_buffers(&self, count: i32) -> AllenResult<()> {
+        // Count what is about to be unqueued before it disappears from AL_BUFFERS_PROCESSED.
+        self.count_played_buffers()?;
+
         let _buffers = vec![0u32; count as usize]; // This will be discarded.
 
+        let _lock = self.context.make_current();
         unsafe { alSourceUnqueueBuffers(self.handle, count, _buffers.as_ptr() as *mut u32) };
 
-        check_al_error()
+        check_al_error()?;
+        self.last_processed
+            .set(self.last_processed.get().saturating_sub(count as u64));
+
+        Ok(())
     }
 }
 
 impl Drop for Source {
     fn drop(&mut self) {
+        if let Some(category) = self.category.get_mut().take() {
+            self.context.unregister_category_source(&category, self.handle);
+        }
+        self.context.unregister_source(self.handle);
+
+        let _lock = self.context.make_current();
         unsafe { alDeleteSources(1, &self.handle) }
         if let Err(err) = check_al_error() {
             println!("WARNING: Source drop failed! {}", err);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context, BufferData, Channels, Samples};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn pitch_semitones() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+
+        source.set_pitch_semitones(12.0).unwrap();
+        assert!((source.pitch().unwrap() - 2.0).abs() < 1e-4);
+        assert!((source.pitch_semitones().unwrap() - 12.0).abs() < 1e-3);
+
+        source.set_pitch_semitones(-12.0).unwrap();
+        assert!((source.pitch().unwrap() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ab_loop_wraps() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100 * 4);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+
+        source
+            .set_ab_loop(Duration::from_secs(1), Duration::from_secs(2))
+            .unwrap();
+        source.play().unwrap();
+
+        // Jump past the end instead of waiting for playback to get there.
+        source.set_time_in_samples(44100 * 2 + 100).unwrap();
+        source.pump().unwrap();
+
+        let offset = source.time_in_samples().unwrap();
+        assert!((44100..44100 * 2).contains(&offset), "offset {}", offset);
+        assert_eq!(source.state().unwrap(), SourceState::Playing);
+    }
+
+    #[test]
+    fn anti_click_fades_in() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.set_anti_click(Duration::from_millis(10));
+
+        source.play().unwrap();
+        assert!(source.output_gain().unwrap() < 0.01);
+
+        source.update(Duration::from_millis(5)).unwrap();
+        assert!((source.output_gain().unwrap() - 0.5).abs() < 0.01);
+
+        source.update(Duration::from_millis(5)).unwrap();
+        assert!((source.output_gain().unwrap() - 1.0).abs() < 0.01);
+        assert!(!source.is_fading());
+    }
+
+    #[test]
+    fn seamless_loop_keeps_playing() {
+        let Some(context) = test_context() else { return };
+        // 50 ms, so the test spans several wraps.
+        let buffer = test_buffer(&context, 2205);
+        let source = context.new_source().unwrap();
+
+        source.loop_seamless(&buffer).unwrap();
+        source.play().unwrap();
+
+        for _ in 0..60 {
+            std::thread::sleep(Duration::from_millis(5));
+            source.pump().unwrap();
+            assert_eq!(source.state().unwrap(), SourceState::Playing);
+        }
+    }
+
+    #[test]
+    fn occlusion_sets_direct_and_send_filters() {
+        let Some(context) = test_context() else { return };
+        let Ok(slot) = context.new_effect_slot() else { return };
+        let source = context.new_source().unwrap();
+        source.set_aux_send(0, Some(&slot), None).unwrap();
+
+        source.apply_occlusion(0.5).unwrap();
+
+        let filters = source.filters.borrow();
+        let filters = filters.as_ref().unwrap();
+        let direct: i32 = source.get(AL_DIRECT_FILTER).unwrap();
+        assert_eq!(direct as u32, filters.direct.handle());
+        assert!((filters.direct.get_f(AL_LOWPASS_GAIN).unwrap() - 0.65).abs() < 1e-5);
+        assert!((filters.direct.get_f(AL_LOWPASS_GAINHF).unwrap() - 0.525).abs() < 1e-5);
+
+        let send = &filters.sends[&0];
+        assert!((send.get_f(AL_LOWPASS_GAIN).unwrap() - 0.75).abs() < 1e-5);
+        assert!((send.get_f(AL_LOWPASS_GAINHF).unwrap() - 0.625).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stop_at_device_time() {
+        let Some(context) = test_context() else { return };
+        let Ok(now) = context.device().clock_ns() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.play().unwrap();
+
+        let target = now + 20_000_000;
+        let mut stop = source.stop_at(target).unwrap();
+        assert!(!stop.poll(&source).unwrap());
+
+        let mut stopped_at = None;
+        for _ in 0..1000 {
+            std::thread::sleep(Duration::from_millis(1));
+            if stop.poll(&source).unwrap() {
+                stopped_at = Some(context.device().clock_ns().unwrap());
+                break;
+            }
+        }
+        let stopped_at = stopped_at.expect("the device clock never reached the target");
+
+        assert!(stop.is_done());
+        assert_eq!(source.state().unwrap(), SourceState::Stopped);
+        assert!(stopped_at >= target);
+        assert!(stopped_at - target < 100_000_000, "stopped {} ns late", stopped_at - target);
+    }
+
+    #[test]
+    fn gain_curve_interpolates() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        source.set_gain(0.8).unwrap();
+
+        let keyframes = [
+            (Duration::ZERO, 0.0),
+            (Duration::from_secs(1), 1.0),
+            (Duration::from_secs(2), 0.5),
+        ];
+        source.set_gain_curve(&keyframes, true);
+
+        source.update(Duration::from_millis(500)).unwrap();
+        assert!((source.output_gain().unwrap() - 0.4).abs() < 1e-4);
+
+        source.update(Duration::from_millis(1000)).unwrap();
+        assert!((source.output_gain().unwrap() - 0.6).abs() < 1e-4);
+
+        // Looped back around to 0.5 s.
+        source.update(Duration::from_millis(1000)).unwrap();
+        assert!((source.output_gain().unwrap() - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn playback_rate_compensation() {
+        let Some(context) = test_context() else { return };
+        let buffer = context.new_buffer().unwrap();
+        buffer
+            .data(BufferData::I16(&[0; 2205]), Channels::Mono, 22050)
+            .unwrap();
+        let source = context.new_source().unwrap();
+
+        source.set_playback_rate(44100, &buffer).unwrap();
+        assert!((source.pitch().unwrap() - 2.0).abs() < 1e-5);
+
+        assert!(source.set_playback_rate(0, &buffer).is_err());
+    }
+
+    #[test]
+    fn source_limit_reached() {
+        let Some(context) = test_context() else { return };
+        let Ok(context) = context
+            .device()
+            .create_context_with_attributes(&[(ALC_MONO_SOURCES, 4), (ALC_STEREO_SOURCES, 0)])
+        else {
+            return;
+        };
+
+        let mut sources = Vec::new();
+        let error = loop {
+            match context.new_source() {
+                Ok(source) => sources.push(source),
+                Err(err) => break err,
+            }
+            assert!(sources.len() < 10000, "the source count never ran out");
+        };
+
+        assert!(matches!(error, AllenError::SourceLimitReached), "{:?}", error);
+        assert!(!sources.is_empty());
+    }
+
+    #[test]
+    fn reverb_mix() {
+        let Some(context) = test_context() else { return };
+        let Ok(slot) = context.new_effect_slot() else { return };
+        let source = context.new_source().unwrap();
+
+        source.set_reverb_mix(&slot, 0, 0.5).unwrap();
+
+        assert_eq!(source.aux_sends.borrow()[&0], slot.handle());
+        let filters = source.filters.borrow();
+        let filters = filters.as_ref().unwrap();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((filters.direct.get_f(AL_LOWPASS_GAIN).unwrap() - half).abs() < 1e-5);
+        assert!((filters.sends[&0].get_f(AL_LOWPASS_GAIN).unwrap() - half).abs() < 1e-5);
+    }
+
+    #[test]
+    fn gain_factors_stack() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        source.set_gain(0.8).unwrap();
+
+        source.set_group_gain(0.5).unwrap();
+        source.fade_to(0.5, Duration::from_millis(10), false);
+        source.update(Duration::from_millis(10)).unwrap();
+
+        assert!((source.output_gain().unwrap() - 0.25 * 0.8).abs() < 1e-6);
+        // The base gain is kept apart from the other factors.
+        assert_eq!(source.gain().unwrap(), 0.8);
+
+        source.set_gain(0.4).unwrap();
+        assert!((source.output_gain().unwrap() - 0.25 * 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn loop_duck_dips_at_wrap() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100 * 4);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.set_loop_duck(Duration::from_millis(4));
+        source
+            .set_ab_loop(Duration::from_secs(1), Duration::from_secs(2))
+            .unwrap();
+        source.play().unwrap();
+
+        source.set_time_in_samples(44100 * 2 + 100).unwrap();
+        source.update(Duration::ZERO).unwrap();
+        assert!(source.output_gain().unwrap() < 0.01);
+
+        source.update(Duration::from_millis(2)).unwrap();
+        assert!((source.output_gain().unwrap() - 0.5).abs() < 0.01);
+
+        source.update(Duration::from_millis(2)).unwrap();
+        assert!((source.output_gain().unwrap() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pitch_smoothing_approaches_target() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        assert_eq!(source.pitch().unwrap(), 1.0);
+
+        source.set_pitch_smoothed(2.0, 0.1).unwrap();
+        assert_eq!(source.pitch().unwrap(), 1.0);
+
+        source.update(Duration::from_millis(16)).unwrap();
+        let first = source.pitch().unwrap();
+        assert!(first > 1.0 && first < 2.0, "pitch {}", first);
+
+        source.update(Duration::from_millis(16)).unwrap();
+        let second = source.pitch().unwrap();
+        assert!(second > first && second < 2.0, "pitch {}", second);
+
+        source.update(Duration::from_secs(2)).unwrap();
+        assert_eq!(source.pitch().unwrap(), 2.0);
+    }
+
+    #[test]
+    fn auto_resampler_quality_by_distance() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        if source.set_auto_resampler_quality(2, 0, 10.0, 50.0).is_err() {
+            return;
+        }
+
+        source.set_position([5.0, 0.0, 0.0]).unwrap();
+        source.update(Duration::ZERO).unwrap();
+        assert_eq!(source.resampler().unwrap(), 2);
+
+        source.set_position([100.0, 0.0, 0.0]).unwrap();
+        source.update(Duration::ZERO).unwrap();
+        assert_eq!(source.resampler().unwrap(), 0);
+    }
+
+    #[test]
+    fn distance_model_gain() {
+        assert_eq!(DistanceModel::None.gain(100.0, 1.0, 1000.0, 1.0), 1.0);
+        assert!((DistanceModel::Inverse.gain(4.0, 1.0, 1000.0, 1.0) - 0.25).abs() < 1e-6);
+        assert!((DistanceModel::LinearClamped.gain(2000.0, 0.0, 1000.0, 1.0)).abs() < 1e-6);
+        assert!((DistanceModel::Exponent.gain(2.0, 1.0, 1000.0, 2.0) - 0.25).abs() < 1e-6);
+        // Clamped models don't amplify within the reference distance.
+        assert_eq!(DistanceModel::InverseClamped.gain(0.1, 1.0, 1000.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn is_audible_by_distance() {
+        let Some(context) = test_context() else { return };
+        let near = context.new_source().unwrap();
+        let far = context.new_source().unwrap();
+        near.set_position([1.0, 0.0, 0.0]).unwrap();
+        far.set_position([100.0, 0.0, 0.0]).unwrap();
+
+        let listener = [0.0; 3];
+        let model = DistanceModel::Inverse;
+        assert!(near.is_audible(listener, 0.05, model).unwrap());
+        assert!(!far.is_audible(listener, 0.05, model).unwrap());
+        assert!((far.effective_gain(listener, model).unwrap() - 0.01).abs() < 1e-4);
+    }
+
+    #[test]
+    fn from_transforms() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+
+        let mut prev = [0.0; 16];
+        prev[12..15].copy_from_slice(&[2.0, 0.0, 0.0]);
+        // Turned to face +X, with a scaled Z axis.
+        let mut cur = [0.0; 16];
+        cur[8..11].copy_from_slice(&[-2.0, 0.0, 0.0]);
+        cur[12..15].copy_from_slice(&[4.0, 0.0, -2.0]);
+
+        source.set_from_transforms(&prev, &cur, 0.5).unwrap();
+        assert_eq!(source.position().unwrap(), [4.0, 0.0, -2.0]);
+        assert_eq!(source.velocity().unwrap(), [4.0, 0.0, -4.0]);
+        assert_eq!(source.direction().unwrap(), [1.0, 0.0, 0.0]);
+
+        assert!(matches!(source.set_from_transforms(&prev, &cur, 0.0), Err(AllenError::InvalidValue)));
+    }
+
+    #[test]
+    fn start_at_loop() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100 * 4);
+        let source = context.new_source().unwrap();
+        if buffer.set_loop_points(&[44100, 44100 * 2]).is_err() || source.set_start_at_loop(true).is_err() {
+            return;
+        }
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.play().unwrap();
+
+        // Playback may have moved on a little since.
+        let offset = source.time_in_samples().unwrap();
+        assert!((44100..44100 + 4410).contains(&offset), "offset {}", offset);
+    }
+
+    #[test]
+    fn pitch_limited_clamps_and_bumps_resampler() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        if source.set_resampler(0).is_err() {
+            return;
+        }
+
+        source.set_pitch_limited(4.0, 2.0).unwrap();
+        assert_eq!(source.pitch().unwrap(), 2.0);
+
+        let resamplers = {
+            let _lock = context.make_current();
+            unsafe { alGetInteger(AL_NUM_RESAMPLERS_SOFT) }
+        };
+        if resamplers > 1 {
+            assert_eq!(source.resampler().unwrap(), resamplers - 1);
+        }
+    }
+
+    #[test]
+    fn distance_reverb_gets_wetter_far_away() {
+        let Some(context) = test_context() else { return };
+        let Ok(slot) = context.new_effect_slot() else { return };
+        let source = context.new_source().unwrap();
+        context.listener().set_position([0.0, 0.0, 0.0]).unwrap();
+        let send_gain = || source.filters.borrow().as_ref().unwrap().sends[&0].get_f(AL_LOWPASS_GAIN).unwrap();
+
+        source.set_position([1.0, 0.0, 0.0]).unwrap();
+        source.set_distance_reverb(&slot, 0, 0.2, 0.9, 5.0, 50.0).unwrap();
+        assert!((send_gain() - 0.2).abs() < 1e-3);
+
+        source.set_position([100.0, 0.0, 0.0]).unwrap();
+        source.update(Duration::ZERO).unwrap();
+        assert!((send_gain() - 0.9).abs() < 1e-3);
+    }
+
+    #[test]
+    fn detach_buffer_while_playing() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+        source.play().unwrap();
+
+        source.detach_buffer().unwrap();
+        assert!(matches!(source.state().unwrap(), SourceState::Stopped | SourceState::Initial));
+        assert_eq!(PropertiesContainer::<i32>::get(&source, AL_BUFFER).unwrap(), 0);
+    }
+
+    #[test]
+    fn gain_normalized_boosts_quiet_clip() {
+        let Some(context) = test_context() else { return };
+        let source = context.new_source().unwrap();
+        let clip = |amplitude: f32| {
+            let samples = (0..44100).map(|frame| amplitude * (frame as f32 * 0.0628).sin()).collect();
+            BufferDescriptor::new(Samples::F32(samples), Channels::Mono, 44100)
+        };
+
+        let quiet = clip(0.01);
+        assert!(quiet.integrated_loudness() < -23.0);
+        source.set_gain_normalized(&quiet, -23.0).unwrap();
+        let gain = source.gain().unwrap();
+        assert!(gain > 1.0 && gain <= MAX_NORMALIZED_GAIN, "gain {}", gain);
+
+        // Far too quiet to reach the target, so capped.
+        source.set_gain_normalized(&clip(1e-5), -23.0).unwrap();
+        assert_eq!(source.gain().unwrap(), MAX_NORMALIZED_GAIN);
+    }
+
+    #[test]
+    fn marker_fires_once() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let source = context.new_source().unwrap();
+        source.set_buffer(Some(&buffer)).unwrap();
+
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = fired.clone();
+        source.add_marker(
+            Duration::from_millis(100),
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        );
+
+        source.play().unwrap();
+        source.pump().unwrap();
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+
+        // Jump past the marker instead of waiting for playback to get there.
+        source.set_time_in_samples(44100 / 5).unwrap();
+        source.pump().unwrap();
+        source.pump().unwrap();
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn counts_played_buffers() {
+        let Some(context) = test_context() else { return };
+        // 10 ms each.
+        let buffers: Vec<_> = (0..3).map(|_| test_buffer(&context, 441)).collect();
+        let source = context.new_source().unwrap();
+        source.queue_buffers(&buffers.iter().collect::<Vec<_>>()).unwrap();
+        source.play().unwrap();
+
+        for _ in 0..100 {
+            source.pump().unwrap();
+            if source.state().unwrap() == SourceState::Stopped {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        source.pump().unwrap();
+        assert_eq!(source.total_buffers_played(), 3);
+
+        // Unqueueing doesn't count the buffers again.
+        source.unqueue_buffers(3).unwrap();
+        source.pump().unwrap();
+        assert_eq!(source.total_buffers_played(), 3);
+    }
+
+    #[test]
+    fn stop_does_not_count_unplayed_buffers() {
+        let Some(context) = test_context() else { return };
+        let buffers: Vec<_> = (0..3).map(|_| test_buffer(&context, 44100)).collect();
+        let source = context.new_source().unwrap();
+        source.queue_buffers(&buffers.iter().collect::<Vec<_>>()).unwrap();
+        source.play().unwrap();
+
+        source.stop().unwrap();
+        assert_eq!(source.buffers_processed().unwrap(), 3);
+        source.pump().unwrap();
+        assert_eq!(source.total_buffers_played(), 0);
+    }
+}
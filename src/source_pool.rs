@@ -0,0 +1,218 @@
+use crate::{AllenError, AllenResult, Buffer, Context, Source, SourceState};
+
+/// A voice of a [`SourcePool`].
+struct Voice {
+    source: Source,
+    /// Whether the voice is playing a one-shot, which is reclaimed once it stops.
+    busy: bool,
+    /// When the voice was last started, in pool triggers, so the oldest voice can be stolen. Also identifies the
+    /// sound it's playing for [`SoundInstance`].
+    started: u64,
+}
+
+/// Identifies a sound played with [`SourcePool::play_oneshot`], even after its voice has been reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundInstance {
+    voice: usize,
+    generation: u64,
+}
+
+impl SoundInstance {
+    /// Whether this sound is still playing; false once it has finished or its voice has been stolen.
+    pub fn is_playing(&self, pool: &SourcePool) -> AllenResult<bool> {
+        match pool.voices.get(self.voice) {
+            Some(voice) if voice.busy && voice.started == self.generation => {
+                Ok(voice.source.state()? == SourceState::Playing)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// The source playing this sound, unless its voice has been reused since.
+    pub fn source<'a>(&self, pool: &'a SourcePool) -> Option<&'a Source> {
+        pool.voices
+            .get(self.voice)
+            .filter(|voice| voice.started == self.generation)
+            .map(|voice| &voice.source)
+    }
+}
+
+/// A bounded pool of sources for fire-and-forget sounds. Sources are created as needed, up to `max_voices`;
+/// beyond that, the sound with the lowest [`Source::priority`] is cut off to make room, the oldest one among equals.
+pub struct SourcePool {
+    context: Context,
+    voices: Vec<Voice>,
+    max_voices: usize,
+    triggers: u64,
+}
+
+impl SourcePool {
+    /// Fails with [`AllenError::InvalidValue`] if `max_voices` is zero.
+    pub fn new(context: &Context, max_voices: usize) -> AllenResult<Self> {
+        if max_voices == 0 {
+            return Err(AllenError::InvalidValue);
+        }
+
+        Ok(Self {
+            context: context.clone(),
+            voices: Vec::new(),
+            max_voices,
+            triggers: 0,
+        })
+    }
+
+    /// The number of voices currently playing a sound.
+    pub fn active_voices(&self) -> usize {
+        self.voices.iter().filter(|voice| voice.busy).count()
+    }
+
+    /// The number of sources the pool has created so far.
+    pub fn allocated_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Plays `buffer` at `gain` on a free voice, stealing one if all are busy. The voice is reclaimed once the sound
+    /// has finished. The returned instance tells whether this particular sound is still playing.
+    pub fn play_oneshot(&mut self, buffer: &Buffer, gain: f32) -> AllenResult<SoundInstance> {
+        let index = self.acquire()?;
+        let voice = &self.voices[index];
+
+        voice.source.set_buffer(Some(buffer))?;
+        voice.source.set_gain(gain)?;
+        voice.source.play()?;
+
+        Ok(SoundInstance {
+            voice: index,
+            generation: voice.started,
+        })
+    }
+
+    /// Takes a free voice, stealing one if all are busy, and returns it stopped with priority 0 to be set up and
+    /// played. Like a one-shot, the voice is reclaimed once it stops, so play it before acquiring another one.
+    pub fn acquire_steal(&mut self) -> AllenResult<&Source> {
+        let index = self.acquire()?;
+        Ok(&self.voices[index].source)
+    }
+
+    fn acquire(&mut self) -> AllenResult<usize> {
+        let index = self.free_voice()?;
+
+        self.triggers += 1;
+        let voice = &mut self.voices[index];
+        voice.busy = true;
+        voice.started = self.triggers;
+
+        let source = &voice.source;
+        source.stop_now()?;
+        source.set_priority(0);
+
+        Ok(index)
+    }
+
+    /// Reclaims the voices whose sounds have finished. Called by [`SourcePool::play_oneshot`], but can also be
+    /// called periodically.
+    pub fn sweep(&mut self) -> AllenResult<()> {
+        for voice in self.voices.iter_mut().filter(|voice| voice.busy) {
+            if matches!(voice.source.state()?, SourceState::Stopped | SourceState::Initial) {
+                voice.busy = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds an idle voice, creating or stealing one if needed.
+    fn free_voice(&mut self) -> AllenResult<usize> {
+        self.sweep()?;
+
+        if let Some(index) = self.voices.iter().position(|voice| !voice.busy) {
+            return Ok(index);
+        }
+
+        if self.voices.len() < self.max_voices {
+            match self.context.new_source() {
+                Ok(source) => {
+                    self.voices.push(Voice {
+                        source,
+                        busy: false,
+                        started: 0,
+                    });
+                    return Ok(self.voices.len() - 1);
+                }
+                // The driver ran out of sources before the pool did; fall back to stealing.
+                Err(AllenError::SourceLimitReached) if !self.voices.is_empty() => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(self
+            .voices
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, voice)| (voice.source.priority(), voice.started))
+            .map(|(index, _)| index)
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_buffer, test_context};
+
+    #[test]
+    fn oneshots_stay_within_bounds() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let mut pool = SourcePool::new(&context, 3).unwrap();
+
+        let instances: Vec<_> = (0..5)
+            .map(|_| pool.play_oneshot(&buffer, 0.5).unwrap())
+            .collect();
+
+        assert_eq!(pool.allocated_voices(), 3);
+        assert_eq!(pool.active_voices(), 3);
+        // The two oldest sounds were cut off for the newest ones.
+        assert!(!instances[0].is_playing(&pool).unwrap());
+        assert!(!instances[1].is_playing(&pool).unwrap());
+        assert!(instances[2..]
+            .iter()
+            .all(|instance| instance.is_playing(&pool).unwrap()));
+
+        assert!(SourcePool::new(&context, 0).is_err());
+    }
+
+    #[test]
+    fn steals_lowest_priority_first() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let mut pool = SourcePool::new(&context, 2).unwrap();
+
+        let important = pool.play_oneshot(&buffer, 1.0).unwrap();
+        important.source(&pool).unwrap().set_priority(200);
+        let ambience = pool.play_oneshot(&buffer, 1.0).unwrap();
+        ambience.source(&pool).unwrap().set_priority(10);
+
+        // The ambience is newer, but less important.
+        let newest = pool.play_oneshot(&buffer, 1.0).unwrap();
+        assert!(important.is_playing(&pool).unwrap());
+        assert!(!ambience.is_playing(&pool).unwrap());
+        assert!(newest.is_playing(&pool).unwrap());
+    }
+
+    #[test]
+    fn recycled_voice_retires_old_instance() {
+        let Some(context) = test_context() else { return };
+        let buffer = test_buffer(&context, 44100);
+        let mut pool = SourcePool::new(&context, 1).unwrap();
+
+        let old = pool.play_oneshot(&buffer, 1.0).unwrap();
+        assert!(old.is_playing(&pool).unwrap());
+
+        let fresh = pool.play_oneshot(&buffer, 1.0).unwrap();
+        assert_eq!(fresh.voice, old.voice);
+        assert!(!old.is_playing(&pool).unwrap());
+        assert!(old.source(&pool).is_none());
+        assert!(fresh.is_playing(&pool).unwrap());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{AllenResult, Context, EffectSlot, Source};
+use std::{collections::HashMap, ffi::CString};
+
+/// How much processing a source gets from [`SpatialLod`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LodLevel {
+    /// 3D positioning and the effect send.
+    Full,
+    /// 3D positioning only.
+    Simple,
+    /// No positioning, played as-is. Without extension ``AL_SOFT_source_spatialize``, this is the same as `Simple`.
+    Flat,
+}
+
+/// Level of detail for scenes with many sources: only the nearest ones get the effect send, and far away ones skip
+/// 3D positioning. Call [`SpatialLod::update`] regularly, e.g. once per frame.
+pub struct SpatialLod {
+    context: Context,
+    /// The number of sources at [`LodLevel::Full`].
+    budget: usize,
+    /// Beyond this distance, sources not within the budget are [`LodLevel::Flat`].
+    flat_distance: f32,
+    /// The auxiliary send used for the effect.
+    send: i32,
+    /// Current levels by source handle, so sources are only reconfigured when their level changes.
+    levels: HashMap<u32, LodLevel>,
+}
+
+impl SpatialLod {
+    pub fn new(context: &Context, budget: usize, flat_distance: f32, send: i32) -> Self {
+        Self {
+            context: context.clone(),
+            budget,
+            flat_distance,
+            send,
+            levels: HashMap::new(),
+        }
+    }
+
+    /// Sets how many of the nearest sources get full processing.
+    pub fn set_budget(&mut self, n_full: usize) {
+        self.budget = n_full;
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// The level `source` was given by the last update.
+    pub fn level(&self, source: &Source) -> Option<LodLevel> {
+        self.levels.get(&source.handle()).copied()
+    }
+
+    /// Ranks `sources` by distance to the listener and reconfigures those whose level changed, routing the sources
+    /// at [`LodLevel::Full`] into `slot`. Requires extension ``ALC_EXT_EFX``.
+    pub fn update(&mut self, sources: &[&Source], slot: &EffectSlot) -> AllenResult<()> {
+        let mut distances = Vec::with_capacity(sources.len());
+        for (index, source) in sources.iter().enumerate() {
+            let [x, y, z] = self.context.listener_to_source_vector(source)?;
+            distances.push((index, (x * x + y * y + z * z).sqrt()));
+        }
+        distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let can_flatten = {
+            let _lock = self.context.make_current();
+            crate::is_extension_present(&CString::new("AL_SOFT_source_spatialize").unwrap())?
+        };
+
+        let mut levels = HashMap::with_capacity(sources.len());
+        for (rank, (index, distance)) in distances.into_iter().enumerate() {
+            let source = sources[index];
+            let level = if rank < self.budget {
+                LodLevel::Full
+            } else if distance > self.flat_distance && can_flatten {
+                LodLevel::Flat
+            } else {
+                LodLevel::Simple
+            };
+
+            if self.levels.get(&source.handle()) != Some(&level) {
+                match level {
+                    LodLevel::Full => source.set_aux_send(self.send, Some(slot), None)?,
+                    _ => source.set_aux_send(self.send, None, None)?,
+                }
+                if can_flatten {
+                    source.set_spatialized(level != LodLevel::Flat)?;
+                }
+            }
+
+            levels.insert(source.handle(), level);
+        }
+
+        // Sources no longer passed in are forgotten.
+        self.levels = levels;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_context;
+
+    #[test]
+    fn only_nearest_get_sends() {
+        let Some(context) = test_context() else { return };
+        let Ok(slot) = context.new_effect_slot() else { return };
+        context.listener().set_position([0.0, 0.0, 0.0]).unwrap();
+        // Farthest first, so ranking has to reorder them.
+        let sources: Vec<_> = (0..5)
+            .map(|index| {
+                let source = context.new_source().unwrap();
+                source.set_position([(5 - index) as f32 * 10.0, 0.0, 0.0]).unwrap();
+                source
+            })
+            .collect();
+        let sources: Vec<_> = sources.iter().collect();
+        let mut lod = SpatialLod::new(&context, 2, 1000.0, 0);
+
+        lod.update(&sources, &slot).unwrap();
+        for (index, source) in sources.iter().enumerate() {
+            let nearest = index >= 3;
+            assert_eq!(source.aux_send(0).is_some(), nearest, "source {}", index);
+            assert_eq!(lod.level(source) == Some(LodLevel::Full), nearest, "source {}", index);
+        }
+
+        lod.set_budget(1);
+        lod.update(&sources, &slot).unwrap();
+        assert_eq!(sources.iter().filter(|source| source.aux_send(0).is_some()).count(), 1);
+        assert!(sources[4].aux_send(0).is_some());
+    }
+}